@@ -25,11 +25,29 @@ pub struct PingArgs {
     pub hyper_v: bool,
     pub force_ipv4: bool,
     pub force_ipv6: bool,
+    pub tcp_port: Option<u16>,
+    pub pcap_path: Option<String>,
+    pub ipc_socket: Option<String>,
+    // 客户端模式：连接到另一个进程的 --ipc-socket 而不是自己发起探测
+    pub attach: Option<String>,
+    pub ipc_add_target: Option<String>,
+    pub ipc_remove_target: Option<String>,
+    pub ipc_snapshot_stats: bool,
+    pub ipc_shutdown: bool,
+    pub payload_pattern: Option<String>,
+    pub ranges: Vec<String>,
+    pub max_hosts: u64,
+    pub timestamp_payload: bool,
+    pub traceroute: bool,
+    pub max_hops: u32,
+    pub probes_per_hop: u32,
+    pub mtu_discover: bool,
     pub concurrency: usize,
     pub interval_ms: u64,
     pub deadline_sec: Option<u64>,
     // 输出控制
     pub json_output: bool,
+    pub json_stream: bool,
     pub csv_output: bool,
     pub summary_only: bool,
     pub quiet: bool,
@@ -65,10 +83,27 @@ impl Default for PingArgs {
             hyper_v: false,
             force_ipv4: false,
             force_ipv6: false,
+            tcp_port: None,
+            pcap_path: None,
+            ipc_socket: None,
+            attach: None,
+            ipc_add_target: None,
+            ipc_remove_target: None,
+            ipc_snapshot_stats: false,
+            ipc_shutdown: false,
+            payload_pattern: None,
+            ranges: Vec::new(),
+            max_hosts: 65536,
+            timestamp_payload: false,
+            traceroute: false,
+            max_hops: 30,
+            probes_per_hop: 3,
+            mtu_discover: false,
             concurrency: 64,
             interval_ms: 1000,
             deadline_sec: None,
             json_output: false,
+            json_stream: false,
             csv_output: false,
             summary_only: false,
             quiet: false,
@@ -80,6 +115,25 @@ impl Default for PingArgs {
     }
 }
 
+impl PingArgs {
+    /// Seed a fresh `PingArgs` from a `--config`/`--profile` selection.
+    /// `parse_args` layers CLI flags on top of the returned value afterward,
+    /// since those only touch a field when the matching flag was actually
+    /// passed — so profile values act as defaults the command line can
+    /// still override.
+    pub fn from_profile(profile: &crate::config::TargetProfile) -> Self {
+        let mut args = Self::default();
+        args.targets = profile.targets.clone();
+        if let Some(size) = profile.size { args.size = Some(size); }
+        if let Some(count) = profile.count { args.count = Some(count); }
+        if let Some(timeout) = profile.timeout { args.timeout = Some(timeout); }
+        if let Some(ttl) = profile.ttl { args.ttl = Some(ttl); }
+        if let Some(interval_ms) = profile.interval_ms { args.interval_ms = interval_ms; }
+        if profile.payload_pattern.is_some() { args.payload_pattern = profile.payload_pattern.clone(); }
+        args
+    }
+}
+
 pub fn build_cli() -> Command {
     Command::new("ruping")
         .version("0.2.0")
@@ -100,17 +154,64 @@ pub fn build_cli() -> Command {
         .arg(
             Arg::new("cidr")
                 .long("cidr")
-                .help("Add targets from CIDR (IPv4), e.g. 192.168.1.0/30; can be repeated or comma-separated")
+                .help("Add targets from CIDR (IPv4 or IPv6), e.g. 192.168.1.0/30 or 2001:db8::/120; can be repeated or comma-separated")
                 .value_name("CIDR")
                 .num_args(1..)
                 .value_delimiter(',')
         )
+        .arg(
+            Arg::new("range")
+                .long("range")
+                .help("Add targets from an address range START-END (IPv4 or IPv6); can be repeated or comma-separated")
+                .value_name("START-END")
+                .num_args(1..)
+                .value_delimiter(',')
+        )
+        .arg(
+            Arg::new("max_hosts")
+                .long("max-hosts")
+                .help("Maximum number of hosts to expand from a single --cidr/--range (default 65536)")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64))
+        )
+        .arg(
+            Arg::new("timestamp_payload")
+                .long("timestamp-payload")
+                .help("Embed a send timestamp in the echo payload and validate it on reply (requires -l >= 12); RTT is computed from the echoed timestamp instead of the local clock")
+                .action(ArgAction::SetTrue)
+        )
         .arg(
             Arg::new("continuous")
                 .short('t')
                 .help("Ping the specified host until stopped")
                 .action(ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("traceroute")
+                .long("traceroute")
+                .help("Trace the route to the target by sending echo requests with increasing TTL/hop limit")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("max_hops")
+                .long("max-hops")
+                .help("Maximum TTL/hop limit to probe in --traceroute mode (default 30)")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+        )
+        .arg(
+            Arg::new("probes_per_hop")
+                .long("probes-per-hop")
+                .help("Number of probes sent per TTL in --traceroute mode (default 3)")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+        )
+        .arg(
+            Arg::new("mtu_discover")
+                .long("mtu-discover")
+                .help("Discover the path MTU by sending DF-set probes and binary-searching the payload size (uses -l as the search ceiling)")
+                .action(ArgAction::SetTrue)
+        )
         .arg(
             Arg::new("resolve")
                 .short('a')
@@ -230,6 +331,73 @@ pub fn build_cli() -> Command {
                 .help("Force using IPv6")
                 .action(ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("tcp")
+                .long("tcp")
+                .help("TCP SYN ping mode: probe reachability via a TCP handshake to PORT instead of ICMP")
+                .value_name("PORT")
+                .value_parser(clap::value_parser!(u16))
+        )
+        .arg(
+            Arg::new("pcap")
+                .long("pcap")
+                .help("Write every sent/received packet to a libpcap capture file for offline analysis")
+                .value_name("PATH")
+        )
+        .arg(
+            Arg::new("ipc_socket")
+                .long("ipc-socket")
+                .help("Listen for control-plane commands (add-target/remove-target/snapshot-stats/shutdown) on a local Unix domain socket (named pipe on Windows) at PATH, so another invocation of ruping can drive or poll this one instead of spawning a duplicate prober")
+                .value_name("PATH")
+        )
+        .arg(
+            Arg::new("attach")
+                .long("attach")
+                .help("Client mode: connect to another ruping process's --ipc-socket PATH and run exactly one of --add-target/--remove-target/--snapshot-stats/--shutdown against it, instead of starting a prober of our own")
+                .value_name("PATH")
+        )
+        .arg(
+            Arg::new("add_target")
+                .long("add-target")
+                .help("With --attach: ask the attached process to start probing TARGET")
+                .value_name("TARGET")
+        )
+        .arg(
+            Arg::new("remove_target")
+                .long("remove-target")
+                .help("With --attach: ask the attached process to stop probing TARGET")
+                .value_name("TARGET")
+        )
+        .arg(
+            Arg::new("snapshot_stats")
+                .long("snapshot-stats")
+                .help("With --attach: print the attached process's current per-host stats as JSON and exit")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("shutdown")
+                .long("shutdown")
+                .help("With --attach: ask the attached process to shut down gracefully")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("Load named target profiles from a TOML config file (used together with --profile)")
+                .value_name("PATH")
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .help("Select a [profiles.NAME] section from --config to seed targets and options from; CLI flags still override the profile's values")
+                .value_name("NAME")
+        )
+        .arg(
+            Arg::new("payload_pattern")
+                .long("payload-pattern")
+                .help("Fill the ICMP payload by repeating the first byte of PATTERN instead of the default 'a' fill byte")
+                .value_name("PATTERN")
+        )
         .arg(
             Arg::new("concurrency")
                 .short('P')
@@ -264,6 +432,12 @@ pub fn build_cli() -> Command {
                 .help("Output results in CSV format (suppresses per-reply printing)")
                 .action(ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("json_stream")
+                .long("json-stream")
+                .help("Emit one compact NDJSON object per reply/timeout as it happens, plus one per-host summary object when that host's run ends (none while it is still being probed), instead of a single document at the end (suppresses per-reply text printing)")
+                .action(ArgAction::SetTrue)
+        )
         .arg(
             Arg::new("summary_only")
                 .long("summary-only")
@@ -306,9 +480,19 @@ pub fn build_cli() -> Command {
 
 pub fn parse_args() -> anyhow::Result<PingArgs> {
     let matches = build_cli().get_matches();
-    
-    let mut args = PingArgs::default();
-    
+
+    let config_path = matches.get_one::<String>("config");
+    let profile_name = matches.get_one::<String>("profile");
+    let mut args = match (config_path, profile_name) {
+        (Some(path), Some(name)) => {
+            let config = crate::config::Config::load(path)?;
+            PingArgs::from_profile(config.profile(name)?)
+        }
+        (Some(_), None) => return Err(anyhow::anyhow!("--config requires --profile to select a section from it")),
+        (None, Some(_)) => return Err(anyhow::anyhow!("--profile requires --config to load it from")),
+        (None, None) => PingArgs::default(),
+    };
+
     if let Some(ts) = matches.get_many::<String>("target") {
         args.targets = ts.cloned().collect();
     }
@@ -326,6 +510,7 @@ pub fn parse_args() -> anyhow::Result<PingArgs> {
     args.force_ipv4 = matches.get_flag("force_ipv4");
     args.force_ipv6 = matches.get_flag("force_ipv6");
     args.json_output = matches.get_flag("json");
+    args.json_stream = matches.get_flag("json_stream");
     args.csv_output = matches.get_flag("csv");
     args.summary_only = matches.get_flag("summary_only");
     args.quiet = matches.get_flag("quiet");
@@ -382,6 +567,50 @@ pub fn parse_args() -> anyhow::Result<PingArgs> {
         args.strict_source_route = Some(hosts.cloned().collect());
     }
     
+    if let Some(port) = matches.get_one::<u16>("tcp") {
+        args.tcp_port = Some(*port);
+    }
+
+    if let Some(path) = matches.get_one::<String>("pcap") {
+        args.pcap_path = Some(path.clone());
+    }
+
+    if let Some(path) = matches.get_one::<String>("ipc_socket") {
+        args.ipc_socket = Some(path.clone());
+    }
+
+    if let Some(path) = matches.get_one::<String>("attach") {
+        args.attach = Some(path.clone());
+    }
+    if let Some(target) = matches.get_one::<String>("add_target") {
+        args.ipc_add_target = Some(target.clone());
+    }
+    if let Some(target) = matches.get_one::<String>("remove_target") {
+        args.ipc_remove_target = Some(target.clone());
+    }
+    args.ipc_snapshot_stats = matches.get_flag("snapshot_stats");
+    args.ipc_shutdown = matches.get_flag("shutdown");
+
+    if let Some(pattern) = matches.get_one::<String>("payload_pattern") {
+        args.payload_pattern = Some(pattern.clone());
+    }
+
+    if let Some(range_vals) = matches.get_many::<String>("range") {
+        args.ranges = range_vals.cloned().collect();
+    }
+    if let Some(max_hosts) = matches.get_one::<u64>("max_hosts") {
+        args.max_hosts = *max_hosts;
+    }
+    args.timestamp_payload = matches.get_flag("timestamp_payload");
+    args.traceroute = matches.get_flag("traceroute");
+    if let Some(max_hops) = matches.get_one::<u32>("max_hops") {
+        args.max_hops = *max_hops;
+    }
+    if let Some(probes) = matches.get_one::<u32>("probes_per_hop") {
+        args.probes_per_hop = *probes;
+    }
+    args.mtu_discover = matches.get_flag("mtu_discover");
+
     if let Some(cc) = matches.get_one::<u32>("concurrency") {
         let v = (*cc).clamp(1, 256) as usize;
         args.concurrency = v;
@@ -402,14 +631,37 @@ pub fn parse_args() -> anyhow::Result<PingArgs> {
         args.count = None; // Continuous mode overrides count
     }
 
-    // Ensure we have at least one target source (positional, file, or cidr)
-    if args.targets.is_empty() && args.targets_file.is_none() && args.cidrs.is_empty() {
-        return Err(anyhow::anyhow!("No targets provided. Specify targets, --file, or --cidr."));
+    // --attach is a client-mode switch: it drives another process's
+    // --ipc-socket instead of starting a prober, so none of the normal
+    // target/output validation below applies to it.
+    if args.attach.is_some() {
+        let actions = [
+            args.ipc_add_target.is_some(),
+            args.ipc_remove_target.is_some(),
+            args.ipc_snapshot_stats,
+            args.ipc_shutdown,
+        ].iter().filter(|b| **b).count();
+        if actions != 1 {
+            return Err(anyhow::anyhow!("--attach requires exactly one of --add-target/--remove-target/--snapshot-stats/--shutdown"));
+        }
+        return Ok(args);
+    }
+    if args.ipc_add_target.is_some() || args.ipc_remove_target.is_some() || args.ipc_snapshot_stats || args.ipc_shutdown {
+        return Err(anyhow::anyhow!("--add-target/--remove-target/--snapshot-stats/--shutdown require --attach"));
+    }
+
+    // Ensure we have at least one target source (positional, file, cidr, range,
+    // or --ipc-socket, which can receive its first targets later via add-target)
+    if args.targets.is_empty() && args.targets_file.is_none() && args.cidrs.is_empty() && args.ranges.is_empty() && args.ipc_socket.is_none() {
+        return Err(anyhow::anyhow!("No targets provided. Specify targets, --file, --cidr, --range, or --ipc-socket."));
     }
     // Validate output
     if args.json_output && args.csv_output {
         return Err(anyhow::anyhow!("--json and --csv cannot be used together"));
     }
+    if args.json_stream && (args.json_output || args.csv_output) {
+        return Err(anyhow::anyhow!("--json-stream cannot be combined with --json or --csv"));
+    }
     
     Ok(args)
 }
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::fs;
+
+/// Packets need at least this many bytes to carry `--timestamp-payload`'s
+/// magic tag + nanosecond timestamp (see `icmp::packet::TIMESTAMP_HEADER_LEN`,
+/// which isn't public — this mirrors its value so profiles can be checked
+/// up front instead of only warning once a run is already under way).
+const MIN_PACKET_SIZE: u32 = 12;
+
+/// One named, reusable ping configuration loaded from a `--config` TOML
+/// file. CLI flags always win over whatever a profile sets: `cli::parse_args`
+/// seeds `PingArgs` from the chosen profile via `PingArgs::from_profile`,
+/// then mutates it with whatever the user actually passed on the command line.
+#[derive(Debug, Clone, Default)]
+pub struct TargetProfile {
+    pub name: String,
+    pub targets: Vec<String>,
+    pub size: Option<u32>,
+    pub count: Option<u32>,
+    pub timeout: Option<u32>,
+    pub ttl: Option<u32>,
+    pub interval_ms: Option<u64>,
+    pub payload_pattern: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub profiles: HashMap<String, TargetProfile>,
+}
+
+impl Config {
+    /// Load and parse a small TOML subset: `[profiles.NAME]` sections with
+    /// `key = value` pairs (strings, integers, and `["a","b"]` string
+    /// arrays). Hand-rolled rather than pulling in a TOML crate — the shape
+    /// this file ever needs is tiny and fixed, matching this codebase's habit
+    /// of hand-building/parsing its own wire formats (see `ipc::protocol`,
+    /// `main::build_json`/`json_escape`) instead of adding a dependency.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("无法读取配置文件 {}: {}", path, e))?;
+        Self::parse(&content)
+    }
+
+    fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut profiles: HashMap<String, TargetProfile> = HashMap::new();
+        let mut current: Option<TargetProfile> = None;
+
+        for (idx, raw_line) in content.lines().enumerate() {
+            let lineno = idx + 1;
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                if let Some(profile) = current.take() {
+                    profiles.insert(profile.name.clone(), profile);
+                }
+                let header = &line[1..line.len() - 1];
+                let name = header.strip_prefix("profiles.")
+                    .ok_or_else(|| anyhow::anyhow!("第 {} 行: 只支持 [profiles.NAME] 格式的分段", lineno))?;
+                current = Some(TargetProfile { name: name.to_string(), ..Default::default() });
+                continue;
+            }
+
+            let profile = current.as_mut()
+                .ok_or_else(|| anyhow::anyhow!("第 {} 行: 键值对必须位于某个 [profiles.NAME] 分段内", lineno))?;
+
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("第 {} 行: 无法解析 'key = value'", lineno))?;
+            let key = key.trim();
+            let value = value.trim();
+
+            match key {
+                "targets" => profile.targets = parse_string_array(value)
+                    .ok_or_else(|| anyhow::anyhow!("第 {} 行: targets 必须是字符串数组", lineno))?,
+                "size" => profile.size = Some(parse_u32(value, lineno)?),
+                "count" => profile.count = Some(parse_u32(value, lineno)?),
+                "timeout" => profile.timeout = Some(parse_u32(value, lineno)?),
+                "ttl" => profile.ttl = Some(parse_u32(value, lineno)?),
+                "interval_ms" => profile.interval_ms = Some(parse_u32(value, lineno)? as u64),
+                "payload_pattern" => profile.payload_pattern = Some(
+                    parse_string(value).ok_or_else(|| anyhow::anyhow!("第 {} 行: payload_pattern 必须是字符串", lineno))?
+                ),
+                other => return Err(anyhow::anyhow!("第 {} 行: 未知字段 '{}'", lineno, other)),
+            }
+        }
+        if let Some(profile) = current.take() {
+            profiles.insert(profile.name.clone(), profile);
+        }
+
+        Ok(Self { profiles })
+    }
+
+    /// Look up a profile by name, first validating every profile in the
+    /// file so a mistake in an unrelated profile is still caught even on a
+    /// run that doesn't select it.
+    pub fn profile(&self, name: &str) -> anyhow::Result<&TargetProfile> {
+        self.validate()?;
+        self.profiles.get(name).ok_or_else(|| anyhow::anyhow!("配置文件中不存在名为 '{}' 的 profile", name))
+    }
+
+    /// Layered validation beyond `utils::validate_ping_params`'s scalar
+    /// checks: every profile must have at least one target and a packet size
+    /// that can actually hold the optional embedded timestamp. Errors name
+    /// the offending profile and field instead of a bare message.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for profile in self.profiles.values() {
+            if profile.targets.is_empty() {
+                return Err(anyhow::anyhow!("profile '{}' 的字段 'targets': 不能为空", profile.name));
+            }
+            if let Some(size) = profile.size {
+                if size < MIN_PACKET_SIZE {
+                    return Err(anyhow::anyhow!(
+                        "profile '{}' 的字段 'size': {} 字节过小，至少需要 {} 字节才能容纳嵌入时间戳",
+                        profile.name, size, MIN_PACKET_SIZE
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_u32(value: &str, lineno: usize) -> anyhow::Result<u32> {
+    value.parse::<u32>().map_err(|_| anyhow::anyhow!("第 {} 行: '{}' 不是合法的整数", lineno, value))
+}
+
+fn parse_string(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    Some(inner.to_string())
+}
+
+fn parse_string_array(value: &str) -> Option<Vec<String>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?;
+    if inner.trim().is_empty() { return Some(Vec::new()); }
+    inner.split(',').map(|item| parse_string(item.trim())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_profile() {
+        let toml = r#"
+            [profiles.gateways]
+            targets = ["10.0.0.1", "10.0.0.2"]
+            size = 64
+            count = 10
+            timeout = 2000
+            ttl = 64
+            interval_ms = 2500
+            payload_pattern = "ab"
+        "#;
+        let config = Config::parse(toml).unwrap();
+        let profile = config.profiles.get("gateways").unwrap();
+        assert_eq!(profile.targets, vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()]);
+        assert_eq!(profile.size, Some(64));
+        assert_eq!(profile.count, Some(10));
+        assert_eq!(profile.timeout, Some(2000));
+        assert_eq!(profile.ttl, Some(64));
+        assert_eq!(profile.interval_ms, Some(2500));
+        assert_eq!(profile.payload_pattern.as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_targets() {
+        let config = Config::parse("[profiles.empty]\nsize = 32\n").unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("empty"));
+        assert!(err.contains("targets"));
+    }
+
+    #[test]
+    fn test_validate_rejects_undersized_packets() {
+        let toml = "[profiles.tiny]\ntargets = [\"1.1.1.1\"]\nsize = 8\n";
+        let config = Config::parse(toml).unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("size"));
+    }
+
+    #[test]
+    fn test_validate_accepts_interval_shorter_than_timeout() {
+        // spawn_host_task's ping loop is strictly sequential (send, await
+        // reply-or-timeout, sleep interval, send next) so there's never an
+        // overlapping probe to guard against — an interval shorter than the
+        // timeout is an entirely ordinary profile and must not be rejected.
+        let toml = "[profiles.fast]\ntargets = [\"1.1.1.1\"]\ntimeout = 5000\ninterval_ms = 1000\n";
+        let config = Config::parse(toml).unwrap();
+        assert!(config.validate().is_ok());
+    }
+}
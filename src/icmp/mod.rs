@@ -1,5 +1,7 @@
 pub mod packet;
 pub mod socket;
+pub mod options;
+pub mod wire;
 
 #[cfg(windows)]
 pub mod winapi;
@@ -7,15 +9,52 @@ pub mod winapi;
 pub use packet::*;
 pub use socket::*;
 
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr};
 
 #[derive(Debug, Clone)]
 pub struct IcmpResponse {
     pub source: IpAddr,
     pub bytes: u32,
+    /// RTT in milliseconds, or `NAN` when the matched reply's sequence
+    /// doesn't match the one this probe sent and `embedded_rtt_ms` isn't
+    /// available to recover a trustworthy number — see `IcmpSocket::send_ping`.
     pub time_ms: f64,
     pub ttl: u32,
     pub sequence: u16,
+    /// Router hops recorded via the IPv4 record-route option (`-r`), if requested.
+    pub recorded_route: Vec<Ipv4Addr>,
+    /// Timestamps recorded via the IPv4 timestamp option (`-s`), if requested.
+    pub timestamps: Vec<u32>,
+    /// Set when the echo was sent with an embedded send timestamp and the
+    /// reply's payload failed `IcmpPacket::verify_payload_pattern`, meaning
+    /// something on the path rewrote the payload.
+    pub payload_corrupted: bool,
+    /// RTT computed from the embedded send timestamp echoed back in the
+    /// reply, when the probe was sent with `embed_timestamp`. More robust
+    /// against the reply being matched to a different in-flight probe than
+    /// the locally-tracked send time.
+    pub embedded_rtt_ms: Option<f64>,
+}
+
+/// One hop's response in a `--traceroute` probe chain: either an
+/// intermediate router's Time Exceeded/Destination Unreachable (`reached ==
+/// false`) or the final target's own echo reply (`reached == true`).
+#[derive(Debug, Clone)]
+pub struct TracerouteHop {
+    pub source: IpAddr,
+    pub time_ms: f64,
+    pub reached: bool,
+}
+
+/// Outcome of one `--mtu-discover` probe sent with the Don't-Fragment bit set.
+#[derive(Debug, Clone)]
+pub enum MtuProbeOutcome {
+    /// The probe's payload size fit within the path MTU.
+    Delivered,
+    /// A router along the path dropped the probe and reported Fragmentation
+    /// Needed (IPv4) / Packet Too Big (IPv6), optionally naming the MTU it
+    /// can actually forward.
+    FragmentationNeeded { next_hop_mtu: Option<u32> },
 }
 
 #[derive(Debug)]
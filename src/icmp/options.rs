@@ -0,0 +1,177 @@
+// IPv4 option-area construction and parsing for record-route, timestamp and
+// source-route probes. The Windows ICMP.dll fallback only ever forwards a
+// TTL through `IpOptionInformation` (its tos is left at 0), so these options only do anything
+// useful on the raw-socket path in `socket.rs`, which crafts the full IPv4
+// header (and therefore the option area) itself.
+
+use std::net::Ipv4Addr;
+
+pub const IPOPT_RECORD_ROUTE: u8 = 7;
+pub const IPOPT_TIMESTAMP: u8 = 68;
+pub const IPOPT_LOOSE_SOURCE_ROUTE: u8 = 131;
+pub const IPOPT_STRICT_SOURCE_ROUTE: u8 = 137;
+
+/// Byte length `build_record_route(count)` will produce, without building it
+/// — used to validate the combined option area up front.
+pub fn record_route_len(count: u32) -> usize {
+    3 + 4 * count.max(1) as usize
+}
+
+/// Byte length `build_timestamp(count, flags)` will produce, without
+/// building it.
+pub fn timestamp_len(count: u32, flags: u8) -> usize {
+    let slot_size = if flags == 0 { 4 } else { 8 };
+    4 + slot_size * count.max(1) as usize
+}
+
+/// Byte length `build_source_route` will produce for `hop_count` hops plus
+/// the trailing destination slot, without building it.
+pub fn source_route_len(hop_count: usize) -> usize {
+    3 + 4 * (hop_count + 1)
+}
+
+/// Reserve a record-route option with `count` zero-filled 4-byte slots for
+/// routers to stamp their address into as the packet is forwarded.
+pub fn build_record_route(count: u32) -> Vec<u8> {
+    let count = count.max(1) as usize;
+    let len = 3 + 4 * count;
+    let mut opt = Vec::with_capacity(len);
+    opt.push(IPOPT_RECORD_ROUTE);
+    opt.push(len as u8);
+    opt.push(4); // pointer: first slot is empty, 1-indexed
+    opt.extend(std::iter::repeat(0u8).take(4 * count));
+    opt
+}
+
+/// Build a timestamp option. `flags` follows RFC 791: 0 = timestamps only,
+/// 1 = preceded by the recording router's address, 3 = prespecified hop list.
+pub fn build_timestamp(count: u32, flags: u8) -> Vec<u8> {
+    let count = count.max(1) as usize;
+    let slot_size = if flags == 0 { 4 } else { 8 };
+    let len = 4 + slot_size * count;
+    let mut opt = Vec::with_capacity(len);
+    opt.push(IPOPT_TIMESTAMP);
+    opt.push(len as u8);
+    opt.push(5); // pointer: first slot is empty, 1-indexed
+    opt.push(flags & 0x0f);
+    opt.extend(std::iter::repeat(0u8).take(slot_size * count));
+    opt
+}
+
+/// Build a loose (131) or strict (137) source-route option carrying the
+/// resolved hop list, with the final destination appended as the trailing
+/// slot the way classic `ping -j`/`-k` does.
+pub fn build_source_route(hosts: &[Ipv4Addr], destination: Ipv4Addr, strict: bool) -> Vec<u8> {
+    let option_type = if strict { IPOPT_STRICT_SOURCE_ROUTE } else { IPOPT_LOOSE_SOURCE_ROUTE };
+    let len = 3 + 4 * (hosts.len() + 1);
+    let mut opt = Vec::with_capacity(len);
+    opt.push(option_type);
+    opt.push(len as u8);
+    opt.push(4); // pointer: first hop to process
+    for host in hosts {
+        opt.extend_from_slice(&host.octets());
+    }
+    opt.extend_from_slice(&destination.octets());
+    opt
+}
+
+/// Decode the router hops stamped into a record-route option reply. Zero
+/// entries (slots never filled in) are dropped from the result.
+pub fn parse_record_route(option: &[u8]) -> Vec<Ipv4Addr> {
+    parse_ipv4_slots(option, 3, 4)
+}
+
+/// Decode the 32-bit timestamps out of a timestamp option reply. When the
+/// flags say each slot also carries the recording router's address, that
+/// address is skipped and only the timestamp is returned.
+pub fn parse_timestamps(option: &[u8]) -> Vec<u32> {
+    if option.len() < 4 { return Vec::new(); }
+    let flags = option[3] & 0x0f;
+    let slot_size = if flags == 0 { 4 } else { 8 };
+    let mut out = Vec::new();
+    let mut i = 4;
+    while i + slot_size <= option.len() {
+        let ts_offset = if flags == 0 { i } else { i + 4 };
+        if ts_offset + 4 > option.len() { break; }
+        let ts = u32::from_be_bytes([option[ts_offset], option[ts_offset + 1], option[ts_offset + 2], option[ts_offset + 3]]);
+        if ts != 0 { out.push(ts); }
+        i += slot_size;
+    }
+    out
+}
+
+fn parse_ipv4_slots(option: &[u8], start: usize, stride: usize) -> Vec<Ipv4Addr> {
+    let mut out = Vec::new();
+    let mut i = start;
+    while i + 4 <= option.len() {
+        let addr = Ipv4Addr::new(option[i], option[i + 1], option[i + 2], option[i + 3]);
+        if !addr.is_unspecified() { out.push(addr); }
+        i += stride;
+    }
+    out
+}
+
+/// Walk the option area of a received IPv4 header (the bytes between the
+/// fixed 20-byte header and the end of the IHL-declared header length) and
+/// split out record-route and timestamp option bodies by type octet.
+pub fn scan_options(options: &[u8]) -> (Option<&[u8]>, Option<&[u8]>) {
+    let mut record_route = None;
+    let mut timestamp = None;
+    let mut i = 0;
+    while i < options.len() {
+        let opt_type = options[i];
+        if opt_type == 0 { break; } // end of options list
+        if opt_type == 1 { i += 1; continue; } // no-op
+        if i + 1 >= options.len() { break; }
+        let len = options[i + 1] as usize;
+        if len < 2 || i + len > options.len() { break; }
+        let body = &options[i..i + len];
+        match opt_type {
+            IPOPT_RECORD_ROUTE => record_route = Some(body),
+            IPOPT_TIMESTAMP => timestamp = Some(body),
+            _ => {}
+        }
+        i += len;
+    }
+    (record_route, timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_route_roundtrip() {
+        let mut opt = build_record_route(2);
+        assert_eq!(opt.len(), 11);
+        opt[3] = 10; opt[4] = 0; opt[5] = 0; opt[6] = 1;
+        let hops = parse_record_route(&opt);
+        assert_eq!(hops, vec!["10.0.0.1".parse::<Ipv4Addr>().unwrap()]);
+    }
+
+    #[test]
+    fn test_timestamp_roundtrip() {
+        let mut opt = build_timestamp(1, 0);
+        assert_eq!(opt.len(), 8);
+        opt[4] = 0; opt[5] = 0; opt[6] = 0x03; opt[7] = 0xe8;
+        assert_eq!(parse_timestamps(&opt), vec![1000]);
+    }
+
+    #[test]
+    fn test_len_helpers_match_builders() {
+        assert_eq!(record_route_len(2), build_record_route(2).len());
+        assert_eq!(timestamp_len(1, 0), build_timestamp(1, 0).len());
+        assert_eq!(timestamp_len(1, 1), build_timestamp(1, 1).len());
+        let hosts = vec!["192.168.1.1".parse().unwrap()];
+        assert_eq!(source_route_len(hosts.len()), build_source_route(&hosts, "192.168.1.254".parse().unwrap(), true).len());
+    }
+
+    #[test]
+    fn test_source_route_layout() {
+        let hosts = vec!["192.168.1.1".parse().unwrap()];
+        let dest = "192.168.1.254".parse().unwrap();
+        let opt = build_source_route(&hosts, dest, true);
+        assert_eq!(opt[0], IPOPT_STRICT_SOURCE_ROUTE);
+        assert_eq!(opt.len(), 3 + 4 * 2);
+    }
+}
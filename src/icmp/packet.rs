@@ -6,6 +6,15 @@ pub const ICMP_ECHO_REPLY: u8 = 0;
 pub const ICMPV6_ECHO_REQUEST: u8 = 128;
 pub const ICMPV6_ECHO_REPLY: u8 = 129;
 
+/// Fill byte used for the portion of the payload that isn't the embedded
+/// timestamp, same as the plain `new_echo_request` fill ('a' like Windows ping).
+pub const PAYLOAD_FILL_BYTE: u8 = 0x61;
+/// Marks a payload as carrying an embedded send timestamp (big-endian u64,
+/// nanoseconds) rather than being pure fill bytes.
+pub const TIMESTAMP_MAGIC: u32 = 0x5250_4e47; // "RPNG"
+/// Bytes consumed by the magic tag + timestamp at the front of the payload.
+const TIMESTAMP_HEADER_LEN: usize = 12;
+
 #[derive(Debug, Clone)]
 pub struct IcmpPacket {
     pub icmp_type: u8,
@@ -18,9 +27,16 @@ pub struct IcmpPacket {
 
 impl IcmpPacket {
     pub fn new_echo_request(identifier: u16, sequence: u16, payload_size: usize, is_ipv6: bool) -> Self {
+        Self::new_echo_request_with_fill(identifier, sequence, payload_size, is_ipv6, PAYLOAD_FILL_BYTE)
+    }
+
+    /// Like `new_echo_request`, but repeats `fill_byte` instead of the
+    /// hardcoded 'a' — used for a `--payload-pattern`/profile-supplied fill
+    /// byte instead of the Windows-ping-like default.
+    pub fn new_echo_request_with_fill(identifier: u16, sequence: u16, payload_size: usize, is_ipv6: bool, fill_byte: u8) -> Self {
         let icmp_type = if is_ipv6 { ICMPV6_ECHO_REQUEST } else { ICMP_ECHO_REQUEST };
-        let payload = vec![0x61; payload_size]; // Fill with 'a' characters like Windows ping
-        
+        let payload = vec![fill_byte; payload_size];
+
         let mut packet = Self {
             icmp_type,
             code: 0,
@@ -29,11 +45,65 @@ impl IcmpPacket {
             sequence,
             payload,
         };
-        
+
         packet.calculate_checksum();
         packet
     }
-    
+
+    /// Like `new_echo_request`, but embeds a big-endian nanosecond send
+    /// timestamp (tagged with `TIMESTAMP_MAGIC`) at the front of the payload
+    /// so the reply's RTT can be recovered directly from the echoed bytes
+    /// instead of a locally-tracked send time. Falls back to a plain fill
+    /// payload when `payload_size` is too small to hold the tag.
+    pub fn new_echo_request_with_timestamp(
+        identifier: u16,
+        sequence: u16,
+        payload_size: usize,
+        is_ipv6: bool,
+        send_time_ns: u64,
+    ) -> Self {
+        let icmp_type = if is_ipv6 { ICMPV6_ECHO_REQUEST } else { ICMP_ECHO_REQUEST };
+        let mut payload = vec![PAYLOAD_FILL_BYTE; payload_size];
+        if payload_size >= TIMESTAMP_HEADER_LEN {
+            payload[0..4].copy_from_slice(&TIMESTAMP_MAGIC.to_be_bytes());
+            payload[4..12].copy_from_slice(&send_time_ns.to_be_bytes());
+        }
+
+        let mut packet = Self {
+            icmp_type,
+            code: 0,
+            checksum: 0,
+            identifier,
+            sequence,
+            payload,
+        };
+
+        packet.calculate_checksum();
+        packet
+    }
+
+    /// Recover the send timestamp embedded by `new_echo_request_with_timestamp`,
+    /// if the payload is tagged with `TIMESTAMP_MAGIC`.
+    pub fn extract_timestamp(&self) -> Option<u64> {
+        if self.payload.len() < TIMESTAMP_HEADER_LEN {
+            return None;
+        }
+        if u32::from_be_bytes(self.payload[0..4].try_into().unwrap()) != TIMESTAMP_MAGIC {
+            return None;
+        }
+        Some(u64::from_be_bytes(self.payload[4..12].try_into().unwrap()))
+    }
+
+    /// Confirm the fill bytes after the embedded timestamp are still intact,
+    /// i.e. nothing on the wire rewrote the payload. Only meaningful for
+    /// packets built by `new_echo_request_with_timestamp`.
+    pub fn verify_payload_pattern(&self) -> bool {
+        if self.extract_timestamp().is_none() {
+            return false;
+        }
+        self.payload[TIMESTAMP_HEADER_LEN..].iter().all(|&b| b == PAYLOAD_FILL_BYTE)
+    }
+
     pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
         if data.len() < 8 {
             return Err(anyhow::anyhow!("ICMP packet too short"));
@@ -110,6 +180,35 @@ impl IcmpPacket {
         let bytes = self.to_bytes();
         Self::compute_checksum(&bytes) == 0
     }
+
+    /// For an ICMP error message (IPv4 Destination Unreachable/Time
+    /// Exceeded/Parameter Problem, or the IPv6 equivalents), recover the
+    /// identifier/sequence of the echo request quoted in the error body so
+    /// it can still be matched against an in-flight probe.
+    pub fn quoted_echo(&self, is_ipv6: bool) -> Option<(u16, u16)> {
+        let is_error = if is_ipv6 {
+            matches!(self.icmp_type, 1 | 2 | 3 | 4)
+        } else {
+            matches!(self.icmp_type, 3 | 4 | 5 | 11 | 12)
+        };
+        if !is_error {
+            return None;
+        }
+        crate::icmp::wire::parse_quoted_echo(&self.payload, is_ipv6).map(|q| (q.identifier, q.sequence))
+    }
+
+    /// Recover the next-hop MTU advertised by an IPv4 Fragmentation Needed
+    /// message (type 3, code 4 — RFC 1191 stuffs it into the normally-unused
+    /// bytes 6-7, which land in our `sequence` field) or an IPv6 Packet Too
+    /// Big message (type 2, code 0 — the full 32-bit MTU spans what we parse
+    /// as `identifier`/`sequence`).
+    pub fn next_hop_mtu(&self) -> Option<u32> {
+        match (self.icmp_type, self.code) {
+            (3, 4) => Some(self.sequence as u32),
+            (2, 0) => Some(((self.identifier as u32) << 16) | self.sequence as u32),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -145,4 +244,60 @@ mod tests {
         packet.calculate_checksum();
         assert!(packet.verify_checksum());
     }
+
+    #[test]
+    fn test_timestamp_roundtrip() {
+        let packet = IcmpPacket::new_echo_request_with_timestamp(1234, 1, 32, false, 123_456_789);
+        let bytes = packet.to_bytes();
+        let parsed = IcmpPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.extract_timestamp(), Some(123_456_789));
+        assert!(parsed.verify_payload_pattern());
+    }
+
+    #[test]
+    fn test_timestamp_too_small_falls_back_to_plain_fill() {
+        let packet = IcmpPacket::new_echo_request_with_timestamp(1234, 1, 4, false, 123_456_789);
+        assert_eq!(packet.extract_timestamp(), None);
+        assert!(!packet.verify_payload_pattern());
+        assert!(packet.payload.iter().all(|&b| b == PAYLOAD_FILL_BYTE));
+    }
+
+    #[test]
+    fn test_plain_echo_request_has_no_timestamp() {
+        let packet = IcmpPacket::new_echo_request(1234, 1, 32, false);
+        assert_eq!(packet.extract_timestamp(), None);
+        assert!(!packet.verify_payload_pattern());
+    }
+
+    #[test]
+    fn test_next_hop_mtu_ipv4_fragmentation_needed() {
+        let packet = IcmpPacket {
+            icmp_type: 3,
+            code: 4,
+            checksum: 0,
+            identifier: 0,
+            sequence: 1480,
+            payload: Vec::new(),
+        };
+        assert_eq!(packet.next_hop_mtu(), Some(1480));
+    }
+
+    #[test]
+    fn test_next_hop_mtu_ipv6_packet_too_big() {
+        let packet = IcmpPacket {
+            icmp_type: 2,
+            code: 0,
+            checksum: 0,
+            identifier: 0,
+            sequence: 1280,
+            payload: Vec::new(),
+        };
+        assert_eq!(packet.next_hop_mtu(), Some(1280));
+    }
+
+    #[test]
+    fn test_next_hop_mtu_absent_for_echo_reply() {
+        let packet = IcmpPacket::new_echo_request(1234, 1, 32, false);
+        assert_eq!(packet.next_hop_mtu(), None);
+    }
 }
@@ -1,29 +1,136 @@
 use socket2::{Domain, Protocol, Socket, Type};
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
-use crate::icmp::{IcmpPacket, IcmpResponse};
+use crate::icmp::{IcmpError, IcmpPacket, IcmpResponse};
+use crate::icmp::options;
+use crate::icmp::wire;
+use crate::pcap::PcapWriter;
+
+/// IPv4 options to stamp onto an echo request, mirroring the Windows ping
+/// `-r`/`-s`/`-j`/`-k` flags. All fields are `None` for a plain echo.
+#[derive(Debug, Clone, Default)]
+pub struct Ipv4OptionRequest {
+    pub record_route_hops: Option<u32>,
+    pub timestamp_hops: Option<u32>,
+    pub timestamp_flags: u8,
+    pub loose_source_route: Option<Vec<Ipv4Addr>>,
+    pub strict_source_route: Option<Vec<Ipv4Addr>>,
+}
+
+/// IPv4's IHL header-length field is 4 bits of 32-bit words, so the header
+/// (20 fixed bytes + options) can never exceed 60 bytes — leaving at most
+/// this many bytes for the option area.
+const MAX_IPV4_OPTIONS_LEN: usize = 40;
+
+impl Ipv4OptionRequest {
+    pub fn is_empty(&self) -> bool {
+        self.record_route_hops.is_none()
+            && self.timestamp_hops.is_none()
+            && self.loose_source_route.is_none()
+            && self.strict_source_route.is_none()
+    }
+
+    /// Reject combinations whose combined, 4-byte-padded option area would
+    /// overflow the 40-byte max instead of silently wrapping the IHL field
+    /// (`header_len / 4 & 0x0f` in `send_ping_with_ipv4_options`) into a
+    /// header length that doesn't match the bytes actually on the wire.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut len = 0usize;
+        if let Some(hops) = self.timestamp_hops { len += options::timestamp_len(hops, self.timestamp_flags); }
+        if let Some(hops) = self.record_route_hops { len += options::record_route_len(hops); }
+        if let Some(hosts) = &self.loose_source_route { len += options::source_route_len(hosts.len()); }
+        if let Some(hosts) = &self.strict_source_route { len += options::source_route_len(hosts.len()); }
+        let padded = (len + 3) / 4 * 4;
+        if padded > MAX_IPV4_OPTIONS_LEN {
+            return Err(anyhow::anyhow!(
+                "组合的 IPv4 选项（record-route/timestamp/source-route）总长度为 {} 字节，超过了 IP 头部选项区最多 {} 字节的限制（IHL 字段只能表示最多 60 字节的 IP 头部），请减少 -r/-s/-j/-k 的跳数",
+                padded, MAX_IPV4_OPTIONS_LEN
+            ));
+        }
+        Ok(())
+    }
+
+    fn build_option_area(&self, destination: Ipv4Addr) -> Vec<u8> {
+        let mut opts = Vec::new();
+        if let Some(hops) = self.timestamp_hops { opts.extend(options::build_timestamp(hops, self.timestamp_flags)); }
+        if let Some(hops) = self.record_route_hops { opts.extend(options::build_record_route(hops)); }
+        if let Some(hosts) = &self.loose_source_route { opts.extend(options::build_source_route(hosts, destination, false)); }
+        if let Some(hosts) = &self.strict_source_route { opts.extend(options::build_source_route(hosts, destination, true)); }
+        // IPv4 options must be padded to a 4-byte boundary.
+        while opts.len() % 4 != 0 { opts.push(0); }
+        opts
+    }
+}
+
+/// Which kind of socket `IcmpSocket` ended up opening.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IcmpSocketMode {
+    /// A `SOCK_RAW` socket: sees (and on IPv4, can craft) the full IP header,
+    /// but requires CAP_NET_RAW / administrator privileges.
+    Raw,
+    /// An unprivileged `SOCK_DGRAM` ICMP "ping socket" (Linux only, when the
+    /// process's GID falls inside `net.ipv4.ping_group_range`). The kernel
+    /// assigns the ICMP identifier itself and rewrites it on every outgoing
+    /// packet, and the receive path never sees an IP header, so IPv4 option
+    /// crafting isn't available in this mode.
+    Datagram,
+}
 
 pub struct IcmpSocket {
     socket: Socket,
     is_ipv6: bool,
+    mode: IcmpSocketMode,
 }
 
 impl IcmpSocket {
     pub fn new(is_ipv6: bool) -> anyhow::Result<Self> {
         let domain = if is_ipv6 { Domain::IPV6 } else { Domain::IPV4 };
         let protocol = if is_ipv6 { Protocol::ICMPV6 } else { Protocol::ICMPV4 };
-        
-        let socket = Socket::new(domain, Type::RAW, Some(protocol))
-            .map_err(|e| anyhow::anyhow!("Failed to create raw socket: {}. Administrator privileges may be required.", e))?;
-        
-        // Set socket options
-        socket.set_nonblocking(false)?; // Use blocking mode for simplicity
 
-        // Note: On Windows, Raw ICMP sockets should NOT include IP header
-        // The OS will handle the IP header automatically
-        
-        Ok(Self { socket, is_ipv6 })
+        let raw_err = match Socket::new(domain, Type::RAW, Some(protocol)) {
+            Ok(socket) => {
+                // Set socket options
+                socket.set_nonblocking(false)?; // Use blocking mode for simplicity
+
+                // Note: On Windows, Raw ICMP sockets should NOT include IP header
+                // The OS will handle the IP header automatically
+
+                return Ok(Self { socket, is_ipv6, mode: IcmpSocketMode::Raw });
+            }
+            Err(e) => e,
+        };
+
+        // Unprivileged fallback: Linux "ping sockets" let an ordinary user send/receive
+        // ICMP echoes via a SOCK_DGRAM socket when their GID is in net.ipv4.ping_group_range;
+        // the kernel handles the identifier and IP header entirely on its own.
+        #[cfg(target_os = "linux")]
+        {
+            match Socket::new(domain, Type::DGRAM, Some(protocol)) {
+                Ok(socket) => {
+                    socket.set_nonblocking(false)?;
+                    log::debug!("Raw ICMP socket unavailable ({}), falling back to unprivileged datagram ping socket", raw_err);
+                    return Ok(Self { socket, is_ipv6, mode: IcmpSocketMode::Datagram });
+                }
+                Err(dgram_err) => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to create raw socket: {}. Unprivileged datagram ping socket also unavailable: {} (check net.ipv4.ping_group_range).",
+                        raw_err, dgram_err
+                    ));
+                }
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err(anyhow::anyhow!("Failed to create raw socket: {}. Administrator privileges may be required.", raw_err))
+        }
+    }
+
+    /// Which kind of socket ended up being opened; `Datagram` means we're
+    /// running unprivileged via the Linux ping-socket fallback.
+    pub fn mode(&self) -> IcmpSocketMode {
+        self.mode
     }
     
     pub async fn send_ping(
@@ -33,34 +140,64 @@ impl IcmpSocket {
         sequence: u16,
         payload_size: usize,
         timeout_ms: u32,
+        embed_timestamp: bool,
+        fill_byte: u8,
+        capture: Option<&PcapWriter>,
     ) -> anyhow::Result<IcmpResponse> {
-        let packet = IcmpPacket::new_echo_request(identifier, sequence, payload_size, self.is_ipv6);
+        let packet = if embed_timestamp {
+            // 嵌入时间戳的回复依赖 verify_payload_pattern 校验固定填充字节，
+            // 因此该模式下忽略自定义填充字节
+            let send_time_ns = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0);
+            IcmpPacket::new_echo_request_with_timestamp(identifier, sequence, payload_size, self.is_ipv6, send_time_ns)
+        } else {
+            IcmpPacket::new_echo_request_with_fill(identifier, sequence, payload_size, self.is_ipv6, fill_byte)
+        };
         let packet_bytes = packet.to_bytes();
-        
+
         let target_addr = match target {
             IpAddr::V4(addr) => SocketAddr::new(IpAddr::V4(addr), 0),
             IpAddr::V6(addr) => SocketAddr::new(IpAddr::V6(addr), 0),
         };
-        
+
         let start_time = Instant::now();
 
         // Send the packet
         log::debug!("Sending ICMP packet to {}: {} bytes", target, packet_bytes.len());
         self.socket.send_to(&packet_bytes, &target_addr.into())?;
+        if let Some(w) = capture { w.write_packet(&self.with_synthesized_ip_header(target, &packet_bytes)); }
         log::debug!("ICMP packet sent successfully");
-        
+
         // Wait for response with timeout
         let timeout_duration = Duration::from_millis(timeout_ms as u64);
-        
-        match timeout(timeout_duration, self.receive_response(identifier, sequence)).await {
+
+        match timeout(timeout_duration, self.receive_response(identifier, sequence, capture)).await {
             Ok(Ok(response)) => {
                 let elapsed = start_time.elapsed();
+                // `receive_response` matches on identifier alone, so `response`
+                // can be a stray reply to an earlier/different sequence (dup,
+                // reorder). `elapsed` only measures time since *this* call's
+                // `start_time`, which isn't that reply's real RTT — only trust
+                // it when the sequence actually matches what we just sent, and
+                // fall back to the embedded timestamp (if any) or NAN otherwise
+                // rather than report a fabricated number.
+                let time_ms = match response.embedded_rtt_ms {
+                    Some(rtt) => rtt,
+                    None if response.sequence == sequence => elapsed.as_secs_f64() * 1000.0,
+                    None => f64::NAN,
+                };
                 Ok(IcmpResponse {
                     source: response.source,
                     bytes: payload_size as u32,
-                    time_ms: elapsed.as_secs_f64() * 1000.0,
+                    time_ms,
                     ttl: response.ttl,
-                    sequence,
+                    sequence: response.sequence,
+                    recorded_route: response.recorded_route,
+                    timestamps: response.timestamps,
+                    payload_corrupted: response.payload_corrupted,
+                    embedded_rtt_ms: response.embedded_rtt_ms,
                 })
             }
             Ok(Err(e)) => Err(e),
@@ -68,7 +205,172 @@ impl IcmpSocket {
         }
     }
     
-    async fn receive_response(&self, expected_id: u16, expected_seq: u16) -> anyhow::Result<IcmpResponse> {
+    /// Like `send_ping`, but crafts the full IPv4 header (and option area)
+    /// ourselves with smoltcp's `wire` types so record-route/timestamp/source
+    /// route options are actually emitted on the wire, instead of being
+    /// silently dropped the way the WinAPI fallback drops them.
+    pub async fn send_ping_with_ipv4_options(
+        &self,
+        target: IpAddr,
+        source: Ipv4Addr,
+        identifier: u16,
+        sequence: u16,
+        payload_size: usize,
+        timeout_ms: u32,
+        ttl: u8,
+        tos: u8,
+        ip_options: &Ipv4OptionRequest,
+        embed_timestamp: bool,
+        fill_byte: u8,
+        capture: Option<&PcapWriter>,
+    ) -> anyhow::Result<IcmpResponse> {
+        let dest = match target {
+            IpAddr::V4(addr) => addr,
+            IpAddr::V6(_) => return Err(anyhow::anyhow!("IPv4 options are not applicable to an IPv6 target")),
+        };
+
+        if ip_options.is_empty() {
+            return self.send_ping(target, identifier, sequence, payload_size, timeout_ms, embed_timestamp, fill_byte, capture).await;
+        }
+        if self.mode != IcmpSocketMode::Raw {
+            return Err(anyhow::anyhow!("IPv4 options (record-route/timestamp/source-route) require a raw socket; the unprivileged datagram ping socket can't set IP_HDRINCL"));
+        }
+        ip_options.validate()?;
+
+        use smoltcp::phy::ChecksumCapabilities;
+        use smoltcp::wire::{Icmpv4Packet, Icmpv4Repr, Ipv4Address, Ipv4Packet, Ipv4Repr};
+
+        let checksum_caps = ChecksumCapabilities::default();
+        let option_bytes = ip_options.build_option_area(dest);
+
+        // Build the ICMP echo request payload (identifier/sequence/data) via smoltcp.
+        let echo_payload = if embed_timestamp {
+            let send_time_ns = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0);
+            IcmpPacket::new_echo_request_with_timestamp(identifier, sequence, payload_size, false, send_time_ns).payload
+        } else {
+            vec![fill_byte; payload_size]
+        };
+        let icmp_repr = Icmpv4Repr::EchoRequest {
+            ident: identifier,
+            seq_no: sequence,
+            data: &echo_payload,
+        };
+        let mut icmp_buf = vec![0u8; icmp_repr.buffer_len()];
+        let mut icmp_packet = Icmpv4Packet::new_unchecked(&mut icmp_buf);
+        icmp_repr.emit(&mut icmp_packet, &checksum_caps);
+
+        // Build the base (option-free) IPv4 header, then splice the option
+        // area in and patch IHL/total length/checksum by hand, since smoltcp's
+        // `Ipv4Repr` models only the fixed 20-byte header.
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address::from(source),
+            dst_addr: Ipv4Address::from(dest),
+            next_header: smoltcp::wire::IpProtocol::Icmp,
+            payload_len: icmp_buf.len(),
+            hop_limit: ttl,
+        };
+        let header_len = 20 + option_bytes.len();
+        let mut packet_buf = vec![0u8; header_len + icmp_buf.len()];
+        {
+            let mut ip_packet = Ipv4Packet::new_unchecked(&mut packet_buf[..20]);
+            ipv4_repr.emit(&mut ip_packet, &checksum_caps);
+        }
+        packet_buf[20..header_len].copy_from_slice(&option_bytes);
+        packet_buf[header_len..].copy_from_slice(&icmp_buf);
+        packet_buf[0] = 0x40 | ((header_len / 4) as u8 & 0x0f); // version 4, IHL in 32-bit words
+        packet_buf[1] = tos;
+        let total_len = packet_buf.len() as u16;
+        packet_buf[2..4].copy_from_slice(&total_len.to_be_bytes());
+        packet_buf[10] = 0;
+        packet_buf[11] = 0;
+        let header_checksum = Self::compute_ipv4_header_checksum(&packet_buf[..header_len]);
+        packet_buf[10..12].copy_from_slice(&header_checksum.to_be_bytes());
+
+        let target_addr = SocketAddr::new(target, 0);
+        let start_time = Instant::now();
+
+        self.socket.set_header_included(true)?;
+        log::debug!("Sending crafted ICMP packet to {}: {} bytes ({} bytes of IP options)", target, packet_buf.len(), option_bytes.len());
+        let send_result = self.socket.send_to(&packet_buf, &target_addr.into());
+        self.socket.set_header_included(false)?;
+        send_result?;
+        if let Some(w) = capture { w.write_packet(&packet_buf); }
+
+        let timeout_duration = Duration::from_millis(timeout_ms as u64);
+        match timeout(timeout_duration, self.receive_response(identifier, sequence, capture)).await {
+            Ok(Ok(mut response)) => {
+                let elapsed = start_time.elapsed();
+                // See the identical reasoning in `send_ping`.
+                response.time_ms = match response.embedded_rtt_ms {
+                    Some(rtt) => rtt,
+                    None if response.sequence == sequence => elapsed.as_secs_f64() * 1000.0,
+                    None => f64::NAN,
+                };
+                response.bytes = payload_size as u32;
+                Ok(response)
+            }
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(anyhow::anyhow!("Request timed out")),
+        }
+    }
+
+    /// Whether a reply's ICMP identifier should be treated as ours. On a raw
+    /// socket we chose the identifier ourselves, so compare exactly; on a
+    /// Linux datagram ping socket the kernel assigns and rewrites the
+    /// identifier on send, bypassing our own `generate_identifier`, and only
+    /// ever delivers us replies to our own conversation, so any identifier
+    /// read back from the header is accepted.
+    fn identifier_matches(&self, actual: u16, expected: u16) -> bool {
+        self.mode == IcmpSocketMode::Datagram || actual == expected
+    }
+
+    /// Best-effort source address for a capture-only synthesized IP header,
+    /// on paths where the OS picks/owns the real one and we never see it
+    /// directly. Falls back to the unspecified address (still a valid,
+    /// parseable header, just without a real source) when it can't be
+    /// determined — e.g. an unbound socket before its first send.
+    fn local_ip(&self) -> IpAddr {
+        self.socket.local_addr().ok()
+            .and_then(|a| a.as_socket())
+            .map(|a| a.ip())
+            .unwrap_or(if self.is_ipv6 { IpAddr::V6(Ipv6Addr::UNSPECIFIED) } else { IpAddr::V4(Ipv4Addr::UNSPECIFIED) })
+    }
+
+    /// Prepend a real IP header to `payload` for capture purposes. Plain
+    /// `send_ping` hands the OS only the bare ICMP message (a non-`IP_HDRINCL`
+    /// socket prepends the header itself on the wire), so without this the
+    /// pcap record would be bare ICMP bytes under a `LINKTYPE_RAW` global
+    /// header that promises a full IP datagram — unparseable by Wireshark.
+    fn with_synthesized_ip_header(&self, target: IpAddr, payload: &[u8]) -> Vec<u8> {
+        let mut packet = match target {
+            IpAddr::V4(dst) => {
+                let src = match self.local_ip() { IpAddr::V4(a) => a, IpAddr::V6(_) => Ipv4Addr::UNSPECIFIED };
+                wire::build_minimal_ipv4_header(src, dst, 64, wire::IPPROTO_ICMP, payload.len())
+            }
+            IpAddr::V6(dst) => {
+                let src = match self.local_ip() { IpAddr::V6(a) => a, IpAddr::V4(_) => Ipv6Addr::UNSPECIFIED };
+                wire::build_minimal_ipv6_header(src, dst, 64, wire::IPPROTO_ICMPV6, payload.len())
+            }
+        };
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn compute_ipv4_header_checksum(header: &[u8]) -> u16 {
+        let mut sum: u32 = 0;
+        let mut i = 0;
+        while i + 1 < header.len() {
+            sum += ((header[i] as u32) << 8) | header[i + 1] as u32;
+            i += 2;
+        }
+        while (sum >> 16) != 0 { sum = (sum & 0xFFFF) + (sum >> 16); }
+        !(sum as u16)
+    }
+
+    async fn receive_response(&self, expected_id: u16, expected_seq: u16, capture: Option<&PcapWriter>) -> anyhow::Result<IcmpResponse> {
         use std::mem::MaybeUninit;
 
         loop {
@@ -94,58 +396,343 @@ impl IcmpSocket {
                 Some(addr) => addr.ip(),
                 None => continue,
             };
+            let received = &buffer_data[..bytes_received];
 
-            // Parse the received packet
-            // On Windows, Raw ICMP socket may or may not include IP header
-            // Try both with and without IP header offset
-            log::debug!("Analyzing packet: {} bytes, first byte: 0x{:02x}", bytes_received, buffer_data[0]);
-
-            let icmp_data = if bytes_received >= 28 && buffer_data[0] >> 4 == 4 {
-                // Looks like we have an IP header (version 4)
-                log::debug!("Packet includes IP header, skipping 20 bytes");
-                &buffer_data[20..bytes_received] // Skip 20-byte IPv4 header
-            } else if bytes_received >= 8 {
-                // No IP header, direct ICMP data
-                log::debug!("Packet is direct ICMP data");
-                &buffer_data[0..bytes_received]
+            // Parse the real IP header instead of guessing a fixed 20-byte
+            // offset, so IPv4 packets carrying options (IHL > 5) and the
+            // true IPv6 hop limit are both handled correctly.
+            // NOTE: on Linux, a raw ICMPv6 socket's receive buffer does not
+            // include the IPv6 header at all (unlike raw ICMPv4 sockets) -
+            // the kernel strips it and the real hop limit can only be
+            // recovered via the IPV6_RECVHOPLIMIT ancillary data on a
+            // recvmsg() call, which socket2's recv_from doesn't expose. The
+            // Ipv6Repr::parse below will therefore almost always hit the
+            // Err branch for IPv6 and report the conservative 64 fallback
+            // rather than the path's actual hop limit; it's left in place
+            // for the (rare) platforms that do hand back the header.
+            let (icmp_data, ttl, recorded_route, timestamps, has_ip_header) = if self.is_ipv6 {
+                match wire::Ipv6Repr::parse(received) {
+                    Ok(ip6) => (&received[ip6.payload_offset..], ip6.hop_limit, Vec::new(), Vec::new(), true),
+                    Err(_) => (received, 64u8, Vec::new(), Vec::new(), false), // some platforms omit the IPv6 header on raw sockets
+                }
             } else {
-                log::debug!("Packet too short: {} bytes", bytes_received);
-                continue; // Packet too short
+                match wire::Ipv4Repr::parse(received) {
+                    Ok(ip4) => {
+                        let (rr, ts) = options::scan_options(&received[20..ip4.payload_offset]);
+                        (
+                            &received[ip4.payload_offset..],
+                            ip4.ttl,
+                            rr.map(options::parse_record_route).unwrap_or_default(),
+                            ts.map(options::parse_timestamps).unwrap_or_default(),
+                            true,
+                        )
+                    }
+                    Err(_) => (received, 64u8, Vec::new(), Vec::new(), false),
+                }
             };
 
-            log::debug!("ICMP data: {} bytes, type: {}, code: {}",
-                       icmp_data.len(),
-                       if icmp_data.len() > 0 { icmp_data[0] } else { 0 },
-                       if icmp_data.len() > 1 { icmp_data[1] } else { 0 });
-
-            match IcmpPacket::from_bytes(icmp_data) {
-                Ok(packet) => {
-                    if packet.is_echo_reply(self.is_ipv6)
-                        && packet.identifier == expected_id
-                        && packet.sequence == expected_seq {
-
-                        let ttl = if self.is_ipv6 {
-                            64 // Default for IPv6, would need to parse hop limit from IPv6 header
-                        } else if bytes_received >= 28 && buffer_data[0] >> 4 == 4 {
-                            buffer_data[8] // TTL field in IPv4 header
-                        } else {
-                            64 // Default TTL when IP header not available
-                        };
-
-                        return Ok(IcmpResponse {
-                            source: source_ip,
-                            bytes: packet.payload.len() as u32,
-                            time_ms: 0.0, // Will be calculated by caller
-                            ttl: ttl as u32,
-                            sequence: packet.sequence,
-                        });
-                    }
+            // `received` is only a real IP datagram when the socket (or
+            // platform) actually handed one back; on the common IPv6 case
+            // (and the rare IPv4 fallback above) it's bare ICMP(v6) bytes,
+            // which would make the pcap record unparseable under the
+            // `LINKTYPE_RAW` global header without a synthesized one.
+            if let Some(w) = capture {
+                if has_ip_header {
+                    w.write_packet(received);
+                } else {
+                    let header = match source_ip {
+                        IpAddr::V6(src) => {
+                            let dst = match self.local_ip() { IpAddr::V6(a) => a, IpAddr::V4(_) => Ipv6Addr::UNSPECIFIED };
+                            wire::build_minimal_ipv6_header(src, dst, ttl, wire::IPPROTO_ICMPV6, icmp_data.len())
+                        }
+                        IpAddr::V4(src) => {
+                            let dst = match self.local_ip() { IpAddr::V4(a) => a, IpAddr::V6(_) => Ipv4Addr::UNSPECIFIED };
+                            wire::build_minimal_ipv4_header(src, dst, ttl, wire::IPPROTO_ICMP, icmp_data.len())
+                        }
+                    };
+                    let mut full = header;
+                    full.extend_from_slice(icmp_data);
+                    w.write_packet(&full);
                 }
+            }
+
+            if icmp_data.len() < 8 {
+                log::debug!("ICMP payload too short: {} bytes", icmp_data.len());
+                continue;
+            }
+            log::debug!("ICMP data: {} bytes, type: {}, code: {}", icmp_data.len(), icmp_data[0], icmp_data[1]);
+
+            let packet = match IcmpPacket::from_bytes(icmp_data) {
+                Ok(packet) => packet,
                 Err(_) => continue, // Invalid packet, keep listening
+            };
+
+            // Accept any echo reply for our identifier, not just the exact
+            // sequence we're waiting on: a duplicate or a late/out-of-order
+            // reply to an earlier probe can legitimately arrive in this
+            // window, and the caller needs to see it (rather than have us
+            // silently swallow it) to report dup/reorder conditions.
+            if packet.is_echo_reply(self.is_ipv6) && self.identifier_matches(packet.identifier, expected_id) {
+                let embedded_rtt_ms = packet.extract_timestamp().map(|send_time_ns| {
+                    let now_ns = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_nanos() as u64)
+                        .unwrap_or(send_time_ns);
+                    now_ns.saturating_sub(send_time_ns) as f64 / 1_000_000.0
+                });
+                let payload_corrupted = packet.extract_timestamp().is_some() && !packet.verify_payload_pattern();
+                return Ok(IcmpResponse {
+                    source: source_ip,
+                    bytes: packet.payload.len() as u32,
+                    time_ms: 0.0, // Will be calculated by caller
+                    ttl: ttl as u32,
+                    sequence: packet.sequence,
+                    recorded_route,
+                    timestamps,
+                    payload_corrupted,
+                    embedded_rtt_ms,
+                });
+            }
+
+            if let Some((identifier, sequence)) = packet.quoted_echo(self.is_ipv6) {
+                if self.identifier_matches(identifier, expected_id) && sequence == expected_seq {
+                    return Err(Self::icmp_error_for(packet.icmp_type, self.is_ipv6).into());
+                }
+            }
+        }
+    }
+
+    fn icmp_error_for(icmp_type: u8, is_ipv6: bool) -> IcmpError {
+        if is_ipv6 {
+            match icmp_type {
+                1 => IcmpError::DestinationUnreachable,
+                2 => IcmpError::Unknown(icmp_type), // Packet Too Big
+                3 => IcmpError::TimeExceeded,
+                4 => IcmpError::ParameterProblem,
+                other => IcmpError::Unknown(other),
+            }
+        } else {
+            match icmp_type {
+                3 => IcmpError::DestinationUnreachable,
+                4 => IcmpError::SourceQuench,
+                5 => IcmpError::Redirect,
+                11 => IcmpError::TimeExceeded,
+                12 => IcmpError::ParameterProblem,
+                other => IcmpError::Unknown(other),
             }
         }
     }
     
+    /// One hop of a `--traceroute` chain: send a plain echo with TTL/hop
+    /// limit set to `ttl` and wait for either an intermediate router's Time
+    /// Exceeded/Destination Unreachable naming this probe, or the final
+    /// target's own echo reply. Unlike `send_ping`, an error ICMP message is
+    /// not a failure here — it's the expected, useful result.
+    pub async fn send_traceroute_probe(
+        &self,
+        target: IpAddr,
+        identifier: u16,
+        sequence: u16,
+        payload_size: usize,
+        timeout_ms: u32,
+        ttl: u32,
+    ) -> anyhow::Result<crate::icmp::TracerouteHop> {
+        // Linux ping sockets (SOCK_DGRAM) never deliver ICMP error messages
+        // (Time Exceeded/Destination Unreachable) through a plain recv —
+        // the kernel only queues them to the socket's error queue via
+        // IP_RECVERR/MSG_ERRQUEUE, which this socket doesn't set up. Without
+        // that, every intermediate hop would silently time out forever, so
+        // fail clearly instead of producing misleading all-'*' output.
+        if self.mode == IcmpSocketMode::Datagram {
+            return Err(anyhow::anyhow!(
+                "Traceroute 模式需要原始套接字才能接收中间路由器的 ICMP 错误消息，当前使用的是非特权 datagram 套接字（无法接收），请以管理员/root 权限运行或调整 net.ipv4.ping_group_range"
+            ));
+        }
+        self.set_ttl(ttl)?;
+
+        let packet = IcmpPacket::new_echo_request(identifier, sequence, payload_size, self.is_ipv6);
+        let packet_bytes = packet.to_bytes();
+        let target_addr = SocketAddr::new(target, 0);
+        let start_time = Instant::now();
+
+        self.socket.send_to(&packet_bytes, &target_addr.into())?;
+
+        let timeout_duration = Duration::from_millis(timeout_ms as u64);
+        match timeout(timeout_duration, self.receive_hop_reply(identifier, sequence)).await {
+            Ok(Ok((source, reached))) => Ok(crate::icmp::TracerouteHop {
+                source,
+                time_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+                reached,
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(anyhow::anyhow!("Request timed out")),
+        }
+    }
+
+    /// Like `receive_response`, but returns on either an echo reply (the
+    /// probe reached its final target) or a matching quoted-echo error (an
+    /// intermediate router's TTL-expired/unreachable message), reporting
+    /// whoever sent it instead of discarding the source.
+    async fn receive_hop_reply(&self, expected_id: u16, expected_seq: u16) -> anyhow::Result<(IpAddr, bool)> {
+        use std::mem::MaybeUninit;
+
+        loop {
+            let socket_ref = &self.socket;
+            let (bytes_received, source_addr, buffer_data) = tokio::task::spawn_blocking({
+                let socket = socket_ref.try_clone()?;
+                move || {
+                    let mut buffer: [MaybeUninit<u8>; 1024] = unsafe { MaybeUninit::uninit().assume_init() };
+                    let result = socket.recv_from(&mut buffer)?;
+                    let mut data = vec![0u8; result.0];
+                    for i in 0..result.0 {
+                        data[i] = unsafe { buffer[i].assume_init() };
+                    }
+                    Ok::<(usize, socket2::SockAddr, Vec<u8>), std::io::Error>((result.0, result.1, data))
+                }
+            }).await??;
+
+            let source_ip = match source_addr.as_socket() {
+                Some(addr) => addr.ip(),
+                None => continue,
+            };
+            let received = &buffer_data[..bytes_received];
+
+            let icmp_data = if self.is_ipv6 {
+                match wire::Ipv6Repr::parse(received) {
+                    Ok(ip6) => &received[ip6.payload_offset..],
+                    Err(_) => received,
+                }
+            } else {
+                match wire::Ipv4Repr::parse(received) {
+                    Ok(ip4) => &received[ip4.payload_offset..],
+                    Err(_) => received,
+                }
+            };
+
+            if icmp_data.len() < 8 {
+                continue;
+            }
+
+            let packet = match IcmpPacket::from_bytes(icmp_data) {
+                Ok(packet) => packet,
+                Err(_) => continue,
+            };
+
+            if packet.is_echo_reply(self.is_ipv6) && self.identifier_matches(packet.identifier, expected_id) && packet.sequence == expected_seq {
+                return Ok((source_ip, true));
+            }
+
+            if let Some((identifier, sequence)) = packet.quoted_echo(self.is_ipv6) {
+                if self.identifier_matches(identifier, expected_id) && sequence == expected_seq {
+                    return Ok((source_ip, false));
+                }
+            }
+        }
+    }
+
+    /// One probe of a `--mtu-discover` binary search: send a plain echo with
+    /// the Don't-Fragment bit set and report whether it got through or was
+    /// dropped for being too large.
+    pub async fn send_mtu_probe(
+        &self,
+        target: IpAddr,
+        identifier: u16,
+        sequence: u16,
+        payload_size: usize,
+        timeout_ms: u32,
+    ) -> anyhow::Result<crate::icmp::MtuProbeOutcome> {
+        // Same limitation as send_traceroute_probe: a Datagram-mode ping
+        // socket never sees the Fragmentation Needed/Packet Too Big error
+        // this probe depends on, so the binary search would silently lose
+        // its advertised-MTU fast path and fall back to slow full-timeout
+        // probing at every step. Fail clearly instead.
+        if self.mode == IcmpSocketMode::Datagram {
+            return Err(anyhow::anyhow!(
+                "MTU 探测需要原始套接字才能接收 Fragmentation Needed/Packet Too Big 错误消息，当前使用的是非特权 datagram 套接字（无法接收），请以管理员/root 权限运行或调整 net.ipv4.ping_group_range"
+            ));
+        }
+        let packet = IcmpPacket::new_echo_request(identifier, sequence, payload_size, self.is_ipv6);
+        let packet_bytes = packet.to_bytes();
+        let target_addr = SocketAddr::new(target, 0);
+
+        self.socket.set_dontfragment(true)?;
+        let send_result = self.socket.send_to(&packet_bytes, &target_addr.into());
+        self.socket.set_dontfragment(false)?;
+        send_result?;
+
+        let timeout_duration = Duration::from_millis(timeout_ms as u64);
+        match timeout(timeout_duration, self.receive_mtu_reply(identifier, sequence)).await {
+            Ok(Ok(outcome)) => Ok(outcome),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(anyhow::anyhow!("Request timed out")),
+        }
+    }
+
+    /// Like `receive_hop_reply`, but distinguishes a successful echo reply
+    /// from a Fragmentation Needed/Packet Too Big error (surfacing the
+    /// advertised next-hop MTU), and treats any other quoted-echo error as
+    /// a genuine failure.
+    async fn receive_mtu_reply(&self, expected_id: u16, expected_seq: u16) -> anyhow::Result<crate::icmp::MtuProbeOutcome> {
+        use std::mem::MaybeUninit;
+
+        loop {
+            let socket_ref = &self.socket;
+            let (bytes_received, _source_addr, buffer_data) = tokio::task::spawn_blocking({
+                let socket = socket_ref.try_clone()?;
+                move || {
+                    let mut buffer: [MaybeUninit<u8>; 1024] = unsafe { MaybeUninit::uninit().assume_init() };
+                    let result = socket.recv_from(&mut buffer)?;
+                    let mut data = vec![0u8; result.0];
+                    for i in 0..result.0 {
+                        data[i] = unsafe { buffer[i].assume_init() };
+                    }
+                    Ok::<(usize, socket2::SockAddr, Vec<u8>), std::io::Error>((result.0, result.1, data))
+                }
+            }).await??;
+
+            let received = &buffer_data[..bytes_received];
+
+            let icmp_data = if self.is_ipv6 {
+                match wire::Ipv6Repr::parse(received) {
+                    Ok(ip6) => &received[ip6.payload_offset..],
+                    Err(_) => received,
+                }
+            } else {
+                match wire::Ipv4Repr::parse(received) {
+                    Ok(ip4) => &received[ip4.payload_offset..],
+                    Err(_) => received,
+                }
+            };
+
+            if icmp_data.len() < 8 {
+                continue;
+            }
+
+            let packet = match IcmpPacket::from_bytes(icmp_data) {
+                Ok(packet) => packet,
+                Err(_) => continue,
+            };
+
+            if packet.is_echo_reply(self.is_ipv6) && self.identifier_matches(packet.identifier, expected_id) && packet.sequence == expected_seq {
+                return Ok(crate::icmp::MtuProbeOutcome::Delivered);
+            }
+
+            if let Some((identifier, sequence)) = packet.quoted_echo(self.is_ipv6) {
+                if self.identifier_matches(identifier, expected_id) && sequence == expected_seq {
+                    let is_frag_needed = if self.is_ipv6 {
+                        packet.icmp_type == 2
+                    } else {
+                        packet.icmp_type == 3 && packet.code == 4
+                    };
+                    if is_frag_needed {
+                        return Ok(crate::icmp::MtuProbeOutcome::FragmentationNeeded { next_hop_mtu: packet.next_hop_mtu() });
+                    }
+                    return Err(Self::icmp_error_for(packet.icmp_type, self.is_ipv6).into());
+                }
+            }
+        }
+    }
+
     pub fn set_ttl(&self, ttl: u32) -> anyhow::Result<()> {
         if self.is_ipv6 {
             // IPv6 uses unicast hop limit
@@ -172,12 +759,10 @@ impl IcmpSocket {
     }
 }
 
-// Helper function to check if raw socket privileges are available
+/// Whether *some* usable ICMP socket can be opened, raw or the unprivileged
+/// datagram fallback (`IcmpSocket::new` already tries both).
 pub fn check_raw_socket_privileges() -> bool {
-    match IcmpSocket::new(false) {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+    IcmpSocket::new(false).is_ok()
 }
 
 #[cfg(test)]
@@ -198,4 +783,18 @@ mod tests {
         let has_privileges = check_raw_socket_privileges();
         println!("Has raw socket privileges: {}", has_privileges);
     }
+
+    #[test]
+    fn test_ipv4_option_request_validate_rejects_oversized_record_route() {
+        // 15 record-route hops -> 3 + 4*15 = 63 bytes, padded to 64, well
+        // past the 40-byte option-area max (repro from the review comment).
+        let opts = Ipv4OptionRequest { record_route_hops: Some(15), ..Default::default() };
+        assert!(opts.validate().is_err());
+    }
+
+    #[test]
+    fn test_ipv4_option_request_validate_accepts_small_combination() {
+        let opts = Ipv4OptionRequest { record_route_hops: Some(2), ..Default::default() };
+        assert!(opts.validate().is_ok());
+    }
 }
@@ -1,7 +1,7 @@
 // Windows ICMP API implementation as fallback
 // This uses the Windows ICMP.dll which doesn't require Raw Socket privileges
 
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv6Addr};
 use std::time::Instant;
 use crate::icmp::IcmpResponse;
 
@@ -26,6 +26,36 @@ struct IcmpEchoReply {
     options: IpOptionInformation,
 }
 
+// IPv6 counterparts
+#[repr(C)]
+struct In6Addr {
+    bytes: [u8; 16],
+}
+
+#[repr(C)]
+struct SockAddrIn6 {
+    family: i16,
+    port: u16,
+    flowinfo: u32,
+    addr: In6Addr,
+    scope_id: u32,
+}
+
+#[repr(C)]
+struct Ipv6AddressEx {
+    address: [u8; 16],
+    scope_id: u32,
+}
+
+#[repr(C)]
+struct Icmpv6EchoReply {
+    address: Ipv6AddressEx,
+    status: u32,
+    rtt: u32,
+}
+
+const AF_INET6: i16 = 23;
+
 // External Windows API functions
 unsafe extern "system" {
     fn IcmpCreateFile() -> *mut std::ffi::c_void;
@@ -40,22 +70,49 @@ unsafe extern "system" {
         reply_size: u32,
         timeout: u32,
     ) -> u32;
+
+    fn Icmp6CreateFile() -> *mut std::ffi::c_void;
+    fn Icmp6SendEcho2(
+        icmp_handle: *mut std::ffi::c_void,
+        event: *mut std::ffi::c_void,
+        apc_routine: *mut std::ffi::c_void,
+        apc_context: *mut std::ffi::c_void,
+        source_address: *const SockAddrIn6,
+        destination_address: *const SockAddrIn6,
+        request_data: *const u8,
+        request_size: u16,
+        request_options: *const IpOptionInformation,
+        reply_buffer: *mut u8,
+        reply_size: u32,
+        timeout: u32,
+    ) -> u32;
+}
+
+fn sockaddr_in6(addr: Ipv6Addr) -> SockAddrIn6 {
+    SockAddrIn6 {
+        family: AF_INET6,
+        port: 0,
+        flowinfo: 0,
+        addr: In6Addr { bytes: addr.octets() },
+        scope_id: 0,
+    }
 }
 
 pub struct WinApiIcmpSocket {
     handle: *mut std::ffi::c_void,
+    is_ipv6: bool,
 }
 
 impl WinApiIcmpSocket {
-    pub fn new() -> anyhow::Result<Self> {
-        let handle = unsafe { IcmpCreateFile() };
+    pub fn new(is_ipv6: bool) -> anyhow::Result<Self> {
+        let handle = if is_ipv6 { unsafe { Icmp6CreateFile() } } else { unsafe { IcmpCreateFile() } };
         if handle.is_null() {
-            return Err(anyhow::anyhow!("Failed to create ICMP handle"));
+            return Err(anyhow::anyhow!("Failed to create {} ICMP handle", if is_ipv6 { "ICMPv6" } else { "ICMPv4" }));
         }
-        
-        Ok(Self { handle })
+
+        Ok(Self { handle, is_ipv6 })
     }
-    
+
     pub async fn send_ping(
         &self,
         target: IpAddr,
@@ -64,17 +121,25 @@ impl WinApiIcmpSocket {
         payload_size: usize,
         timeout_ms: u32,
         ttl: Option<u32>,
+        source_address: Option<IpAddr>,
+    ) -> anyhow::Result<IcmpResponse> {
+        match target {
+            // IcmpSendEcho has no source-address parameter of its own, so
+            // --source-address can't be honored on the IPv4 WinAPI path.
+            IpAddr::V4(addr) => self.send_ping_v4(addr, payload_size, timeout_ms, ttl),
+            IpAddr::V6(addr) => self.send_ping_v6(target, addr, payload_size, timeout_ms, ttl, source_address),
+        }
+    }
+
+    fn send_ping_v4(
+        &self,
+        target: std::net::Ipv4Addr,
+        payload_size: usize,
+        timeout_ms: u32,
+        ttl: Option<u32>,
     ) -> anyhow::Result<IcmpResponse> {
-        // Only IPv4 is supported by Windows ICMP API
-        let ipv4_addr = match target {
-            IpAddr::V4(addr) => addr,
-            IpAddr::V6(_) => {
-                return Err(anyhow::anyhow!("IPv6 not supported by Windows ICMP API"));
-            }
-        };
-        
         let payload = vec![0x61u8; payload_size]; // Fill with 'a' like Windows ping
-        
+
         let options = IpOptionInformation {
             ttl: ttl.unwrap_or(128) as u8,
             tos: 0,
@@ -82,17 +147,17 @@ impl WinApiIcmpSocket {
             options_size: 0,
             options_data: std::ptr::null_mut(),
         };
-        
+
         // Reply buffer needs to be large enough for reply structure + data
         let reply_size = std::mem::size_of::<IcmpEchoReply>() + payload_size + 8;
         let mut reply_buffer = vec![0u8; reply_size];
-        
+
         let start_time = Instant::now();
-        
+
         let result = unsafe {
             IcmpSendEcho(
                 self.handle,
-                u32::from(ipv4_addr),
+                u32::from(target),
                 payload.as_ptr(),
                 payload.len() as u16,
                 &options,
@@ -101,26 +166,106 @@ impl WinApiIcmpSocket {
                 timeout_ms,
             )
         };
-        
+
         let elapsed = start_time.elapsed();
-        
+
         if result == 0 {
             return Err(anyhow::anyhow!("ICMP request failed or timed out"));
         }
-        
+
         // Parse the reply
         let reply = unsafe { &*(reply_buffer.as_ptr() as *const IcmpEchoReply) };
-        
+
         if reply.status != 0 {
             return Err(anyhow::anyhow!("ICMP error status: {}", reply.status));
         }
-        
+
+        Ok(IcmpResponse {
+            source: IpAddr::V4(target),
+            bytes: payload_size as u32,
+            time_ms: elapsed.as_secs_f64() * 1000.0,
+            ttl: options.ttl as u32,
+            sequence: 0, // Windows API doesn't provide sequence number
+            recorded_route: Vec::new(),
+            timestamps: Vec::new(),
+            payload_corrupted: false,
+            embedded_rtt_ms: None,
+        })
+    }
+
+    fn send_ping_v6(
+        &self,
+        target: IpAddr,
+        target_v6: Ipv6Addr,
+        payload_size: usize,
+        timeout_ms: u32,
+        ttl: Option<u32>,
+        source_address: Option<IpAddr>,
+    ) -> anyhow::Result<IcmpResponse> {
+        let payload = vec![0x61u8; payload_size]; // Fill with 'a' like Windows ping
+
+        let options = IpOptionInformation {
+            ttl: ttl.unwrap_or(128) as u8,
+            tos: 0,
+            flags: 0,
+            options_size: 0,
+            options_data: std::ptr::null_mut(),
+        };
+
+        // Defaults to in6addr_any (let the stack pick); honors --source-address
+        // when the caller set one, same as the raw-socket path's bind_to_interface.
+        let source_v6 = match source_address {
+            Some(IpAddr::V6(addr)) => addr,
+            _ => Ipv6Addr::UNSPECIFIED,
+        };
+        let source = sockaddr_in6(source_v6);
+        let dest = sockaddr_in6(target_v6);
+
+        // Reply buffer needs to be large enough for the ICMPV6_ECHO_REPLY structure + data
+        let reply_size = std::mem::size_of::<Icmpv6EchoReply>() + payload_size + 8;
+        let mut reply_buffer = vec![0u8; reply_size];
+
+        let start_time = Instant::now();
+
+        let result = unsafe {
+            Icmp6SendEcho2(
+                self.handle,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                &source,
+                &dest,
+                payload.as_ptr(),
+                payload.len() as u16,
+                &options,
+                reply_buffer.as_mut_ptr(),
+                reply_size as u32,
+                timeout_ms,
+            )
+        };
+
+        let elapsed = start_time.elapsed();
+
+        if result == 0 {
+            return Err(anyhow::anyhow!("ICMPv6 request failed or timed out"));
+        }
+
+        let reply = unsafe { &*(reply_buffer.as_ptr() as *const Icmpv6EchoReply) };
+
+        if reply.status != 0 {
+            return Err(anyhow::anyhow!("ICMPv6 error status: {}", reply.status));
+        }
+
         Ok(IcmpResponse {
             source: target,
             bytes: payload_size as u32,
             time_ms: elapsed.as_secs_f64() * 1000.0,
             ttl: options.ttl as u32,
             sequence: 0, // Windows API doesn't provide sequence number
+            recorded_route: Vec::new(),
+            timestamps: Vec::new(),
+            payload_corrupted: false,
+            embedded_rtt_ms: None,
         })
     }
 }
@@ -135,9 +280,9 @@ impl Drop for WinApiIcmpSocket {
     }
 }
 
-// Test if Windows ICMP API is available
-pub fn is_winapi_available() -> bool {
-    match WinApiIcmpSocket::new() {
+// Test if the Windows ICMP API is available for the given address family
+pub fn is_winapi_available(is_ipv6: bool) -> bool {
+    match WinApiIcmpSocket::new(is_ipv6) {
         Ok(_) => true,
         Err(_) => false,
     }
@@ -146,18 +291,20 @@ pub fn is_winapi_available() -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_winapi_availability() {
-        let available = is_winapi_available();
-        println!("Windows ICMP API available: {}", available);
+        let available = is_winapi_available(false);
+        println!("Windows ICMP API (v4) available: {}", available);
+        let available_v6 = is_winapi_available(true);
+        println!("Windows ICMP API (v6) available: {}", available_v6);
     }
-    
+
     #[tokio::test]
     async fn test_winapi_ping() {
-        if let Ok(socket) = WinApiIcmpSocket::new() {
+        if let Ok(socket) = WinApiIcmpSocket::new(false) {
             let target = "127.0.0.1".parse().unwrap();
-            match socket.send_ping(target, 0, 0, 32, 4000, None).await {
+            match socket.send_ping(target, 0, 0, 32, 4000, None, None).await {
                 Ok(response) => {
                     println!("WinAPI ping successful: {:?}", response);
                 }
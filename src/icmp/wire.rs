@@ -0,0 +1,243 @@
+// Layered IPv4/IPv6 header parsers, modeled on smoltcp's `Packet`/`Repr`
+// split: a `Repr` knows the logical fields (addresses, TTL/hop limit,
+// payload offset), and `parse()` derives it from raw bytes while honoring
+// variable-length headers instead of assuming a fixed 20-byte IPv4 header
+// or hardcoding the IPv6 hop limit.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+pub const IPPROTO_ICMP: u8 = 1;
+pub const IPPROTO_ICMPV6: u8 = 58;
+
+#[derive(Debug, Clone)]
+pub struct Ipv4Repr {
+    pub src_addr: Ipv4Addr,
+    pub dst_addr: Ipv4Addr,
+    pub ttl: u8,
+    pub protocol: u8,
+    /// Offset of the payload (first byte after the header, including any options).
+    pub payload_offset: usize,
+}
+
+impl Ipv4Repr {
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < 20 {
+            return Err(anyhow::anyhow!("IPv4 header too short"));
+        }
+        if data[0] >> 4 != 4 {
+            return Err(anyhow::anyhow!("not an IPv4 packet"));
+        }
+        let ihl = (data[0] & 0x0f) as usize * 4;
+        if ihl < 20 || data.len() < ihl {
+            return Err(anyhow::anyhow!("invalid IPv4 IHL"));
+        }
+        Ok(Self {
+            src_addr: Ipv4Addr::new(data[12], data[13], data[14], data[15]),
+            dst_addr: Ipv4Addr::new(data[16], data[17], data[18], data[19]),
+            ttl: data[8],
+            protocol: data[9],
+            payload_offset: ihl,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ipv6Repr {
+    pub src_addr: Ipv6Addr,
+    pub dst_addr: Ipv6Addr,
+    pub hop_limit: u8,
+    pub next_header: u8,
+    /// Offset of the payload after walking any extension headers.
+    pub payload_offset: usize,
+}
+
+impl Ipv6Repr {
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < 40 {
+            return Err(anyhow::anyhow!("IPv6 header too short"));
+        }
+        if data[0] >> 4 != 6 {
+            return Err(anyhow::anyhow!("not an IPv6 packet"));
+        }
+        let mut next_header = data[6];
+        let hop_limit = data[7];
+        let mut src_octets = [0u8; 16];
+        src_octets.copy_from_slice(&data[8..24]);
+        let mut dst_octets = [0u8; 16];
+        dst_octets.copy_from_slice(&data[24..40]);
+
+        // Walk extension headers (Hop-by-Hop, Routing, Fragment, Destination
+        // Options) to find where the ICMPv6 payload actually starts.
+        let mut offset = 40;
+        loop {
+            match next_header {
+                0 | 43 | 60 => {
+                    if offset + 2 > data.len() { return Err(anyhow::anyhow!("truncated IPv6 extension header")); }
+                    let ext_next = data[offset];
+                    let ext_len = (data[offset + 1] as usize + 1) * 8;
+                    if offset + ext_len > data.len() { return Err(anyhow::anyhow!("truncated IPv6 extension header")); }
+                    next_header = ext_next;
+                    offset += ext_len;
+                }
+                44 => {
+                    if offset + 8 > data.len() { return Err(anyhow::anyhow!("truncated IPv6 fragment header")); }
+                    next_header = data[offset];
+                    offset += 8;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(Self {
+            src_addr: Ipv6Addr::from(src_octets),
+            dst_addr: Ipv6Addr::from(dst_octets),
+            hop_limit,
+            next_header,
+            payload_offset: offset,
+        })
+    }
+}
+
+/// Build a minimal (option-free) IPv4 header around a payload of
+/// `payload_len` bytes. Used only to synthesize a real header for pcap
+/// capture on paths where the actual wire bytes never carried one (an
+/// ordinary, non-`IP_HDRINCL` send, where the OS prepends the header itself)
+/// — `PcapWriter`'s `LINKTYPE_RAW` records are meaningless without one.
+pub fn build_minimal_ipv4_header(src: Ipv4Addr, dst: Ipv4Addr, ttl: u8, protocol: u8, payload_len: usize) -> Vec<u8> {
+    let mut header = vec![0u8; 20];
+    header[0] = 0x45; // version 4, IHL 5 (20 bytes, no options)
+    header[2..4].copy_from_slice(&((20 + payload_len) as u16).to_be_bytes());
+    header[8] = ttl;
+    header[9] = protocol;
+    header[12..16].copy_from_slice(&src.octets());
+    header[16..20].copy_from_slice(&dst.octets());
+    let checksum = ipv4_checksum(&header);
+    header[10..12].copy_from_slice(&checksum.to_be_bytes());
+    header
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    let mut i = 0;
+    while i + 1 < header.len() {
+        sum += ((header[i] as u32) << 8) | header[i + 1] as u32;
+        i += 2;
+    }
+    while (sum >> 16) != 0 { sum = (sum & 0xFFFF) + (sum >> 16); }
+    !(sum as u16)
+}
+
+/// Build a minimal IPv6 header (no extension headers) around a payload of
+/// `payload_len` bytes, for the same capture-synthesis reason as
+/// `build_minimal_ipv4_header` — on Linux a raw ICMPv6 socket's receive buffer
+/// never includes the real IPv6 header at all (see the comment in
+/// `socket.rs::receive_response`).
+pub fn build_minimal_ipv6_header(src: Ipv6Addr, dst: Ipv6Addr, hop_limit: u8, next_header: u8, payload_len: usize) -> Vec<u8> {
+    let mut header = vec![0u8; 40];
+    header[0] = 0x60; // version 6, traffic class/flow label left zeroed
+    header[4..6].copy_from_slice(&(payload_len as u16).to_be_bytes());
+    header[6] = next_header;
+    header[7] = hop_limit;
+    header[8..24].copy_from_slice(&src.octets());
+    header[24..40].copy_from_slice(&dst.octets());
+    header
+}
+
+/// The identifier/sequence recovered from the 8-byte ICMP echo header quoted
+/// inside a Destination Unreachable / Time Exceeded / Packet Too Big error,
+/// so an error reply to our own probe can still be matched by request id.
+#[derive(Debug, Clone, Copy)]
+pub struct QuotedEcho {
+    pub identifier: u16,
+    pub sequence: u16,
+}
+
+/// Parse the quoted original datagram embedded in an ICMP(v6) error body
+/// (the bytes after the outer 8-byte ICMP header) and recover its echo
+/// identifier/sequence.
+pub fn parse_quoted_echo(error_body: &[u8], is_ipv6: bool) -> Option<QuotedEcho> {
+    let inner_icmp = if is_ipv6 {
+        let inner = Ipv6Repr::parse(error_body).ok()?;
+        &error_body[inner.payload_offset..]
+    } else {
+        let inner = Ipv4Repr::parse(error_body).ok()?;
+        &error_body[inner.payload_offset..]
+    };
+    if inner_icmp.len() < 8 {
+        return None;
+    }
+    Some(QuotedEcho {
+        identifier: u16::from_be_bytes([inner_icmp[4], inner_icmp[5]]),
+        sequence: u16::from_be_bytes([inner_icmp[6], inner_icmp[7]]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_header(ihl_words: u8, ttl: u8) -> Vec<u8> {
+        let mut h = vec![0u8; ihl_words as usize * 4];
+        h[0] = 0x40 | (ihl_words & 0x0f);
+        h[8] = ttl;
+        h[9] = IPPROTO_ICMP;
+        h[12..16].copy_from_slice(&[10, 0, 0, 1]);
+        h[16..20].copy_from_slice(&[10, 0, 0, 2]);
+        h
+    }
+
+    #[test]
+    fn test_ipv4_parse_with_options() {
+        let header = ipv4_header(7, 55); // IHL = 7 words = 28 bytes (8 bytes of options)
+        let repr = Ipv4Repr::parse(&header).unwrap();
+        assert_eq!(repr.payload_offset, 28);
+        assert_eq!(repr.ttl, 55);
+        assert_eq!(repr.src_addr, Ipv4Addr::new(10, 0, 0, 1));
+    }
+
+    #[test]
+    fn test_ipv4_parse_no_options() {
+        let header = ipv4_header(5, 64);
+        let repr = Ipv4Repr::parse(&header).unwrap();
+        assert_eq!(repr.payload_offset, 20);
+        assert_eq!(repr.ttl, 64);
+    }
+
+    #[test]
+    fn test_build_minimal_ipv4_header_roundtrips_through_parse() {
+        let header = build_minimal_ipv4_header(
+            Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2), 55, IPPROTO_ICMP, 8,
+        );
+        let repr = Ipv4Repr::parse(&header).unwrap();
+        assert_eq!(repr.payload_offset, 20);
+        assert_eq!(repr.ttl, 55);
+        assert_eq!(repr.protocol, IPPROTO_ICMP);
+        assert_eq!(repr.src_addr, Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(repr.dst_addr, Ipv4Addr::new(10, 0, 0, 2));
+    }
+
+    #[test]
+    fn test_build_minimal_ipv6_header_roundtrips_through_parse() {
+        let src: Ipv6Addr = "fe80::1".parse().unwrap();
+        let dst: Ipv6Addr = "fe80::2".parse().unwrap();
+        let mut packet = build_minimal_ipv6_header(src, dst, 250, IPPROTO_ICMPV6, 8);
+        packet.extend_from_slice(&[0u8; 8]);
+        let repr = Ipv6Repr::parse(&packet).unwrap();
+        assert_eq!(repr.payload_offset, 40);
+        assert_eq!(repr.hop_limit, 250);
+        assert_eq!(repr.next_header, IPPROTO_ICMPV6);
+        assert_eq!(repr.src_addr, src);
+        assert_eq!(repr.dst_addr, dst);
+    }
+
+    #[test]
+    fn test_ipv6_parse_no_extensions() {
+        let mut h = vec![0u8; 40];
+        h[0] = 0x60;
+        h[6] = IPPROTO_ICMPV6;
+        h[7] = 250;
+        let repr = Ipv6Repr::parse(&h).unwrap();
+        assert_eq!(repr.payload_offset, 40);
+        assert_eq!(repr.hop_limit, 250);
+    }
+}
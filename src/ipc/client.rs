@@ -0,0 +1,36 @@
+use super::protocol::{read_frame, write_frame, IpcCommand};
+
+/// Thin client for driving a running ruping process over its control
+/// socket — used by a second invocation of the binary (or any script)
+/// instead of spawning a duplicate prober.
+pub struct IpcClient {
+    path: String,
+}
+
+impl IpcClient {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub async fn send_command(&self, cmd: IpcCommand) -> anyhow::Result<String> {
+        let request = cmd.to_json();
+        #[cfg(unix)]
+        {
+            use tokio::net::UnixStream;
+            let mut stream = UnixStream::connect(&self.path)
+                .await
+                .map_err(|e| anyhow::anyhow!("无法连接 IPC 套接字 {}: {}", self.path, e))?;
+            write_frame(&mut stream, &request).await?;
+            Ok(read_frame(&mut stream).await?)
+        }
+        #[cfg(windows)]
+        {
+            use tokio::net::windows::named_pipe::ClientOptions;
+            let mut pipe = ClientOptions::new()
+                .open(&self.path)
+                .map_err(|e| anyhow::anyhow!("无法连接命名管道 {}: {}", self.path, e))?;
+            write_frame(&mut pipe, &request).await?;
+            Ok(read_frame(&mut pipe).await?)
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod protocol;
+pub mod server;
+pub mod client;
+
+pub use client::IpcClient;
+pub use protocol::IpcCommand;
+pub use server::{IpcServer, IpcState};
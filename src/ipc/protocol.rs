@@ -0,0 +1,111 @@
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// One request frame sent by an `IpcClient` to the control socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpcCommand {
+    AddTarget { target: String },
+    RemoveTarget { target: String },
+    SnapshotStats,
+    Shutdown,
+}
+
+/// Reply frame sent back for a single `IpcCommand`.
+#[derive(Debug, Clone)]
+pub enum IpcResponse {
+    Ok,
+    Error(String),
+    /// Pre-built JSON array of per-host stats, already escaped.
+    Stats(String),
+}
+
+impl IpcCommand {
+    /// Parse a flat JSON object like `{"cmd":"add-target","target":"8.8.8.8"}`.
+    /// This is a small hand-rolled scanner rather than a general JSON parser —
+    /// the wire format only ever carries a `cmd` field plus one optional
+    /// string argument, so it matches the rest of the codebase's habit of
+    /// building/reading JSON by hand (see `main::build_json`/`json_escape`)
+    /// instead of pulling in a JSON crate.
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let cmd = extract_string_field(json, "cmd").ok_or_else(|| anyhow::anyhow!("缺少 \"cmd\" 字段"))?;
+        match cmd.as_str() {
+            "add-target" => {
+                let target = extract_string_field(json, "target").ok_or_else(|| anyhow::anyhow!("add-target 需要 \"target\" 字段"))?;
+                Ok(IpcCommand::AddTarget { target })
+            }
+            "remove-target" => {
+                let target = extract_string_field(json, "target").ok_or_else(|| anyhow::anyhow!("remove-target 需要 \"target\" 字段"))?;
+                Ok(IpcCommand::RemoveTarget { target })
+            }
+            "snapshot-stats" => Ok(IpcCommand::SnapshotStats),
+            "shutdown" => Ok(IpcCommand::Shutdown),
+            other => Err(anyhow::anyhow!("未知命令 '{}'", other)),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        match self {
+            IpcCommand::AddTarget { target } => format!("{{\"cmd\":\"add-target\",\"target\":\"{}\"}}", json_escape(target)),
+            IpcCommand::RemoveTarget { target } => format!("{{\"cmd\":\"remove-target\",\"target\":\"{}\"}}", json_escape(target)),
+            IpcCommand::SnapshotStats => "{\"cmd\":\"snapshot-stats\"}".to_string(),
+            IpcCommand::Shutdown => "{\"cmd\":\"shutdown\"}".to_string(),
+        }
+    }
+}
+
+impl IpcResponse {
+    pub fn to_json(&self) -> String {
+        match self {
+            IpcResponse::Ok => "{\"status\":\"ok\"}".to_string(),
+            IpcResponse::Error(msg) => format!("{{\"status\":\"error\",\"message\":\"{}\"}}", json_escape(msg)),
+            IpcResponse::Stats(payload) => format!("{{\"status\":\"ok\",\"stats\":{}}}", payload),
+        }
+    }
+}
+
+pub(crate) fn json_escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Extract `"field":"value"` from a flat JSON object by scanning for the key
+/// rather than parsing the whole document.
+fn extract_string_field(json: &str, field: &str) -> Option<String> {
+    let needle = format!("\"{}\"", field);
+    let key_pos = json.find(&needle)?;
+    let after_key = &json[key_pos + needle.len()..];
+    let colon_pos = after_key.find(':')?;
+    let after_colon = after_key[colon_pos + 1..].trim_start();
+    let rest = after_colon.strip_prefix('"')?;
+    let end = rest.find('"')?;
+    Some(rest[..end].to_string())
+}
+
+/// Frames on the wire are a 4-byte little-endian length prefix followed by
+/// that many bytes of UTF-8 JSON, so either side can tell where one message
+/// ends and the next begins on a byte stream (Unix socket / named pipe).
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, payload: &str) -> io::Result<()> {
+    let bytes = payload.as_bytes();
+    writer.write_all(&(bytes.len() as u32).to_le_bytes()).await?;
+    writer.write_all(bytes).await?;
+    writer.flush().await
+}
+
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<String> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf).await?;
+    let len = u32::from_le_bytes(len_buf) as usize;
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).await?;
+    String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,146 @@
+use super::protocol::{json_escape, read_frame, write_frame, IpcCommand, IpcResponse};
+use crate::stats::PingStatistics;
+use crate::utils::ShutdownController;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// State the control socket reads from and writes into, shared between the
+/// `IpcServer` and the scheduler loop that dispatches ping targets in `main`.
+pub struct IpcState {
+    /// Raw target strings accepted via `add-target`, picked up by the
+    /// scheduler the same way `--file`/`--cidr` targets are, once the
+    /// up-front target list is exhausted.
+    new_targets: mpsc::UnboundedSender<String>,
+    /// Names the scheduler should skip the next time it would dispatch them.
+    /// A host already in flight finishes its current run rather than being
+    /// cancelled mid-probe.
+    cancelled: Mutex<HashSet<String>>,
+    /// Latest per-host summary, updated at the same points `--json-stream`
+    /// emits its summary events.
+    stats: Mutex<HashMap<String, (String, PingStatistics)>>,
+}
+
+impl IpcState {
+    pub fn new(new_targets: mpsc::UnboundedSender<String>) -> Self {
+        Self { new_targets, cancelled: Mutex::new(HashSet::new()), stats: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn record_stats(&self, name: &str, ip: &str, stats: &PingStatistics) {
+        self.stats.lock().unwrap().insert(name.to_string(), (ip.to_string(), stats.clone()));
+    }
+
+    pub fn is_cancelled(&self, name: &str) -> bool {
+        self.cancelled.lock().unwrap().contains(name)
+    }
+
+    fn snapshot_json(&self) -> String {
+        let snapshot = self.stats.lock().unwrap();
+        let mut out = String::from("[");
+        for (idx, (name, (ip, s))) in snapshot.iter().enumerate() {
+            if idx > 0 { out.push(','); }
+            out.push_str(&format!(
+                "{{\"name\":\"{}\",\"ip\":\"{}\",\"sent\":{},\"received\":{},\"lost\":{},\"loss_pct\":{:.2},\"avg\":{:.3}}}",
+                json_escape(name), json_escape(ip), s.packets_sent, s.packets_received, s.packets_lost, s.loss_percentage(), s.average_time()
+            ));
+        }
+        out.push(']');
+        out
+    }
+}
+
+/// Control-plane listener: a Unix domain socket on Linux/macOS, a named pipe
+/// on Windows — the same cross-platform split `parity-tokio-ipc` uses — so a
+/// second invocation of the binary (or a script) can drive/query a running
+/// ruping process instead of spawning a duplicate prober.
+pub struct IpcServer {
+    path: String,
+}
+
+impl IpcServer {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub async fn run(self, state: Arc<IpcState>, shutdown: Arc<ShutdownController>) -> anyhow::Result<()> {
+        #[cfg(unix)]
+        { self.run_unix(state, shutdown).await }
+        #[cfg(windows)]
+        { self.run_windows(state, shutdown).await }
+    }
+
+    #[cfg(unix)]
+    async fn run_unix(self, state: Arc<IpcState>, shutdown: Arc<ShutdownController>) -> anyhow::Result<()> {
+        use tokio::net::UnixListener;
+        // 避免上次异常退出遗留的 socket 文件导致 bind 失败
+        let _ = std::fs::remove_file(&self.path);
+        let listener = UnixListener::bind(&self.path)
+            .map_err(|e| anyhow::anyhow!("无法监听 IPC 套接字 {}: {}", self.path, e))?;
+        loop {
+            let (stream, _addr) = listener.accept().await?;
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, state, shutdown).await {
+                    crate::utils::print_warning(&format!("IPC 连接处理失败: {}", e));
+                }
+            });
+        }
+    }
+
+    #[cfg(windows)]
+    async fn run_windows(self, state: Arc<IpcState>, shutdown: Arc<ShutdownController>) -> anyhow::Result<()> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        let mut first_instance = true;
+        loop {
+            let pipe = ServerOptions::new()
+                .first_pipe_instance(first_instance)
+                .create(&self.path)
+                .map_err(|e| anyhow::anyhow!("无法创建命名管道 {}: {}", self.path, e))?;
+            first_instance = false;
+            pipe.connect().await?;
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(pipe, state, shutdown).await {
+                    crate::utils::print_warning(&format!("IPC 连接处理失败: {}", e));
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection<S>(mut stream: S, state: Arc<IpcState>, shutdown: Arc<ShutdownController>) -> anyhow::Result<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    loop {
+        let frame = match read_frame(&mut stream).await {
+            Ok(f) => f,
+            Err(_) => return Ok(()), // 对端断开连接
+        };
+        let response = match IpcCommand::parse(&frame) {
+            Ok(cmd) => dispatch(cmd, &state, &shutdown),
+            Err(e) => IpcResponse::Error(e.to_string()),
+        };
+        write_frame(&mut stream, &response.to_json()).await?;
+    }
+}
+
+fn dispatch(cmd: IpcCommand, state: &IpcState, shutdown: &ShutdownController) -> IpcResponse {
+    match cmd {
+        IpcCommand::AddTarget { target } => match state.new_targets.send(target) {
+            Ok(()) => IpcResponse::Ok,
+            Err(e) => IpcResponse::Error(format!("调度器已停止接收新目标: {}", e)),
+        },
+        IpcCommand::RemoveTarget { target } => {
+            state.cancelled.lock().unwrap().insert(target);
+            IpcResponse::Ok
+        }
+        IpcCommand::SnapshotStats => IpcResponse::Stats(state.snapshot_json()),
+        IpcCommand::Shutdown => {
+            shutdown.trigger();
+            IpcResponse::Ok
+        }
+    }
+}
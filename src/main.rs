@@ -1,19 +1,33 @@
 mod cli;
+mod config;
 mod dns;
 mod icmp;
+mod ipc;
 mod stats;
 mod utils;
 mod netif;
+mod tcpping;
+mod pcap;
 
 use icmp::IcmpSocket;
 use stats::PingStatistics;
 use std::collections::{HashSet, VecDeque};
 use std::fs;
 use std::net::{IpAddr, Ipv4Addr};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
 use tokio::task::JoinSet;
 use tokio::time::{sleep, timeout};
 
+/// How many recently-seen sequence numbers `spawn_host_task` keeps around to
+/// classify a reply as a duplicate, per host. The ping loop only ever has one
+/// probe in flight at a time (each send awaits its reply or timeout before
+/// the next one goes out), so a handful of probes' worth of history is
+/// enough to catch a genuinely late/duplicated reply without growing
+/// unbounded across an arbitrarily long `--continuous` run.
+const SEEN_SEQUENCE_WINDOW: usize = 64;
+
 #[tokio::main]
 async fn main() {
     // 启用调试日志
@@ -22,37 +36,109 @@ async fn main() {
     // 解析参数
     let args = match cli::parse_args() { Ok(a) => a, Err(e) => { utils::exit_with_error(&format!("参数解析错误: {}", e), 1); } };
 
+    // 客户端模式（--attach）：驱动另一个进程的 --ipc-socket 而不是自己探测，
+    // 执行完单条命令就退出
+    if let Some(path) = &args.attach {
+        run_attach_command(path, &args).await;
+        return;
+    }
+
     // 参数校验
     if let Err(e) = utils::validate_ping_params(args.size, args.count, args.timeout, args.ttl) {
         utils::exit_with_error(&e.to_string(), 1);
     }
+    // 提前校验 -r/-s/-j/-k 组合的 IPv4 选项总长度，避免等到实际发包时才
+    // 发现 IHL 字段已经悄悄溢出（见 send_ping_with_ipv4_options 的同一校验）
+    let ipv4_options_probe = icmp::socket::Ipv4OptionRequest {
+        record_route_hops: args.record_route,
+        timestamp_hops: args.timestamp,
+        timestamp_flags: 0,
+        loose_source_route: args.loose_source_route.as_ref().map(|hosts| parse_ipv4_host_list(hosts)),
+        strict_source_route: args.strict_source_route.as_ref().map(|hosts| parse_ipv4_host_list(hosts)),
+    };
+    if let Err(e) = ipv4_options_probe.validate() {
+        utils::exit_with_error(&e.to_string(), 1);
+    }
+    if args.timestamp_payload && args.size.unwrap_or(32) < 12 {
+        utils::print_warning("--timestamp-payload 需要 -l 不小于 12 字节，当前数据包过小，将退化为普通填充负载");
+    }
+
+    // 优雅关闭协调器：第一次 Ctrl+C 广播 Drain（停止新探测、等待在途回复、打印汇总），
+    // 第二次 Ctrl+C 广播 Abort 并立即退出
+    let shutdown = utils::ShutdownController::new();
 
     // 构建目标集合
     let mut targets: Vec<String> = Vec::new();
     if !args.targets.is_empty() { targets.extend(args.targets.clone()); }
     if let Some(file) = &args.targets_file { targets.extend(read_targets_from_file(file)); }
-    for c in &args.cidrs { targets.extend(expand_cidr_ipv4(c)); }
+    for c in &args.cidrs { targets.extend(expand_cidr(c, args.max_hosts)); }
+    for r in &args.ranges { targets.extend(expand_range(r, args.max_hosts)); }
 
     // 去重并保持插入顺序
     let mut seen: HashSet<String> = HashSet::new();
     targets.retain(|t| seen.insert(t.to_string()));
-    if targets.is_empty() { utils::exit_with_error("未提供任何目标。", 1); }
+    // 如果开启了 --ipc-socket，允许以零目标启动——这是"纯粹通过 IPC 喂目标"
+    // 的守护进程的自然起点，后续目标通过 add-target 命令到达
+    if targets.is_empty() && args.ipc_socket.is_none() { utils::exit_with_error("未提供任何目标。", 1); }
 
     // 并发解析目标为 IP
     let mut jobs: VecDeque<HostJob> = VecDeque::new();
     for t in targets {
-        let prefer_v4 = args.force_ipv4;
-        let prefer_v6 = args.force_ipv6;
-        let ip = match t.parse::<IpAddr>() {
-            Ok(ip) => ip,
-            Err(_) => match dns::resolve_hostname(&t, prefer_v4, prefer_v6).await {
-                Ok(ip) => ip,
-                Err(e) => { eprintln!("无法解析主机名 '{}': {}", t, e); continue; }
-            }
-        };
-        jobs.push_back(HostJob { name: t, ip, is_ipv6: ip.is_ipv6() });
+        match resolve_target(t.clone(), args.force_ipv4, args.force_ipv6).await {
+            Some(job) => jobs.push_back(job),
+            None => eprintln!("无法解析主机名 '{}'", t),
+        }
     }
-    if jobs.is_empty() { utils::exit_with_error("没有可用的可解析目标。", 1); }
+    if jobs.is_empty() && args.ipc_socket.is_none() { utils::exit_with_error("没有可用的可解析目标。", 1); }
+
+    // 控制套接字（--ipc-socket）：另一个 ruping 进程可以通过它下发
+    // add-target/remove-target/snapshot-stats/shutdown 命令
+    let (new_targets_tx, mut new_targets_rx) = mpsc::unbounded_channel::<String>();
+    let ipc_state: Option<Arc<ipc::IpcState>> = match &args.ipc_socket {
+        Some(path) => {
+            let state = Arc::new(ipc::IpcState::new(new_targets_tx));
+            let server = ipc::IpcServer::new(path.clone());
+            let server_state = state.clone();
+            let server_shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.run(server_state, server_shutdown).await {
+                    utils::print_warning(&format!("IPC 控制套接字退出: {}", e));
+                }
+            });
+            Some(state)
+        }
+        None => None,
+    };
+
+    // 抓包输出（--pcap）
+    let pcap_writer: Option<Arc<pcap::PcapWriter>> = match &args.pcap_path {
+        Some(path) => match pcap::PcapWriter::create(path) {
+            Ok(w) => Some(Arc::new(w)),
+            Err(e) => utils::exit_with_error(&format!("无法创建 pcap 文件 {}: {}", path, e), 1),
+        },
+        None => None,
+    };
+
+    // --json-stream：为每个回复/超时以及每个主机探测结束时的一条汇总建立一个 NDJSON 事件通道
+    // （主机仍在探测期间不输出汇总），
+    // 写入任务边收边输出，而不是等所有主机跑完后一次性序列化（见 build_json）
+    let (stream_tx, stream_writer): (Option<mpsc::UnboundedSender<String>>, Option<tokio::task::JoinHandle<()>>) = if args.json_stream {
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let out_path = args.output_path.clone();
+        let handle = tokio::spawn(async move {
+            use std::io::Write;
+            let mut file = out_path.as_ref().and_then(|p| fs::File::create(p).ok());
+            while let Some(line) = rx.recv().await {
+                match &mut file {
+                    Some(f) => { let _ = writeln!(f, "{}", line); }
+                    None => println!("{}", line),
+                }
+            }
+        });
+        (Some(tx), Some(handle))
+    } else {
+        (None, None)
+    };
 
     // 并发调度
     let concurrency = args.concurrency.min(256).max(1);
@@ -61,38 +147,64 @@ async fn main() {
     let per_host_interval = Duration::from_millis(args.interval_ms.max(1));
     let count = if args.continuous { u32::MAX } else { args.count.unwrap_or(4) };
 
+    // 是否以"守护进程"模式运行：开启 --ipc-socket 时，即使本地队列和在途
+    // 任务都空了也不应该退出进程（否则后台的 IpcServer 任务会随 main() 返回
+    // 一起被杀掉，第二个 ruping 实例的 add-target/snapshot-stats 就无处可发），
+    // 而是阻塞等待下一个 add-target 命令或显式的 shutdown 命令
+    let daemon_mode = ipc_state.is_some();
+
     // 全局截止时间
     let overall_future = async {
-        let mut set: JoinSet<(String, String, PingStatistics, Vec<(u16, Option<f64>, String)>)> = JoinSet::new();
+        let mut set: JoinSet<(String, String, PingStatistics, Vec<(u16, Option<f64>, String, Option<u32>)>)> = JoinSet::new();
         let mut in_flight: usize = 0;
-        let mut results: Vec<(String, String, PingStatistics, Vec<(u16, Option<f64>, String)>)> = Vec::new();
-
-        // 启动初始批次
-        while in_flight < concurrency && !jobs.is_empty() {
-            let job = jobs.pop_front().unwrap();
-            spawn_host_task(&args, job, payload_size, timeout_ms, per_host_interval, count, &mut set).await;
-            in_flight += 1;
-        }
+        let mut results: Vec<(String, String, PingStatistics, Vec<(u16, Option<f64>, String, Option<u32>)>)> = Vec::new();
+        let mut shutdown_rx = shutdown.subscribe();
+
+        loop {
+            // 派发任务，直到达到并发上限或暂时没有更多可派发的目标
+            while in_flight < concurrency && !shutdown.is_draining() {
+                match dispatch_next(&mut jobs, &mut new_targets_rx, &ipc_state, args.force_ipv4, args.force_ipv6).await {
+                    Some(job) => {
+                        spawn_host_task(&args, job, payload_size, timeout_ms, per_host_interval, count, pcap_writer.clone(), stream_tx.clone(), shutdown.clone(), ipc_state.clone(), &mut set).await;
+                        in_flight += 1;
+                    }
+                    None => break,
+                }
+            }
 
-        // 轮询完成并继续派发
-        while let Some(res) = set.join_next().await {
-            match res {
-                Ok((name, ip, stats, reps)) => { results.push((name, ip, stats, reps)); },
-                Err(e) => { eprintln!("任务执行失败: {}", e); }
+            if in_flight > 0 {
+                if let Some(res) = set.join_next().await {
+                    match res {
+                        Ok((name, ip, stats, reps)) => { results.push((name, ip, stats, reps)); },
+                        Err(e) => { eprintln!("任务执行失败: {}", e); }
+                    }
+                    in_flight -= 1;
+                }
+                continue;
             }
-            in_flight -= 1;
-            if let Some(job) = jobs.pop_front() {
-                spawn_host_task(&args, job, payload_size, timeout_ms, per_host_interval, count, &mut set).await;
-                in_flight += 1;
-            } else if in_flight == 0 {
+
+            // 没有在途任务，也没有排队的目标
+            if !daemon_mode || shutdown.is_draining() {
                 break;
             }
+
+            // 守护模式下队列和并发槽都空闲：阻塞等待下一个 add-target 命令
+            // 或 shutdown 信号，而不是把 overall_future 直接结束掉
+            tokio::select! {
+                Some(raw) = new_targets_rx.recv() => {
+                    match resolve_target(raw.clone(), args.force_ipv4, args.force_ipv6).await {
+                        Some(job) => jobs.push_back(job),
+                        None => eprintln!("无法解析 add-target 提供的主机名 '{}'", raw),
+                    }
+                }
+                _ = shutdown_rx.recv() => {}
+            }
         }
 
         results
     };
 
-    let results: Vec<(String, String, PingStatistics, Vec<(u16, Option<f64>, String)>)> = if let Some(deadline_sec) = args.deadline_sec {
+    let results: Vec<(String, String, PingStatistics, Vec<(u16, Option<f64>, String, Option<u32>)>)> = if let Some(deadline_sec) = args.deadline_sec {
         match timeout(Duration::from_secs(deadline_sec), overall_future).await {
             Ok(res) => res,
             Err(_) => {
@@ -105,10 +217,17 @@ async fn main() {
         overall_future.await
     };
 
+    // 关闭事件通道并等待写入任务把缓冲的事件落盘，再继续批量输出/统计
+    drop(stream_tx);
+    if let Some(handle) = stream_writer { let _ = handle.await; }
+
     // 总体汇总与输出
     let mut total = PingStatistics::new();
     for (_name, _ip, s, _) in &results { total.merge_from(s); }
 
+    if args.json_stream {
+        return;
+    }
     if args.json_output {
         // JSON 输出
         let mut s = build_json(&results, &total, args.include_replies, args.pretty_json);
@@ -147,8 +266,75 @@ async fn main() {
     }
 }
 
+/// Client mode (`--attach PATH`): build the single `IpcCommand` selected by
+/// `cli::parse_args`'s mutually-exclusive action flags, send it to the
+/// running process listening on `PATH`, print the reply and exit — this is
+/// what lets a second invocation of the binary drive/query a running
+/// `--ipc-socket` daemon instead of spawning a duplicate prober.
+async fn run_attach_command(path: &str, args: &cli::PingArgs) {
+    let cmd = if let Some(target) = &args.ipc_add_target {
+        ipc::IpcCommand::AddTarget { target: target.clone() }
+    } else if let Some(target) = &args.ipc_remove_target {
+        ipc::IpcCommand::RemoveTarget { target: target.clone() }
+    } else if args.ipc_snapshot_stats {
+        ipc::IpcCommand::SnapshotStats
+    } else {
+        ipc::IpcCommand::Shutdown
+    };
+
+    let client = ipc::IpcClient::new(path);
+    match client.send_command(cmd).await {
+        Ok(reply) => println!("{}", reply),
+        Err(e) => utils::exit_with_error(&format!("连接 {} 失败: {}", path, e), 1),
+    }
+}
+
 struct HostJob { name: String, ip: IpAddr, is_ipv6: bool }
 
+/// Resolve one target string (literal IP or hostname) into a `HostJob`,
+/// shared by the up-front target list and `add-target` commands arriving
+/// over the `--ipc-socket` control channel.
+async fn resolve_target(target: String, prefer_v4: bool, prefer_v6: bool) -> Option<HostJob> {
+    let ip = match target.parse::<IpAddr>() {
+        Ok(ip) => ip,
+        Err(_) => dns::resolve_hostname(&target, prefer_v4, prefer_v6).await.ok()?,
+    };
+    Some(HostJob { name: target, ip, is_ipv6: ip.is_ipv6() })
+}
+
+/// Pop the next job to dispatch: from the locally queued targets first, then
+/// (once those are exhausted) from targets added live via `add-target`.
+/// Skips anything marked cancelled via `remove-target` rather than dispatching it.
+async fn dispatch_next(
+    jobs: &mut VecDeque<HostJob>,
+    new_targets_rx: &mut mpsc::UnboundedReceiver<String>,
+    ipc_state: &Option<Arc<ipc::IpcState>>,
+    prefer_v4: bool,
+    prefer_v6: bool,
+) -> Option<HostJob> {
+    loop {
+        if let Some(job) = jobs.pop_front() {
+            if let Some(state) = ipc_state {
+                if state.is_cancelled(&job.name) { continue; }
+            }
+            return Some(job);
+        }
+        match new_targets_rx.try_recv() {
+            Ok(raw) => {
+                let job = match resolve_target(raw.clone(), prefer_v4, prefer_v6).await {
+                    Some(job) => job,
+                    None => { eprintln!("无法解析 add-target 提供的主机名 '{}'", raw); continue; }
+                };
+                if let Some(state) = ipc_state {
+                    if state.is_cancelled(&job.name) { continue; }
+                }
+                return Some(job);
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
 async fn spawn_host_task(
     args: &cli::PingArgs,
     job: HostJob,
@@ -156,7 +342,11 @@ async fn spawn_host_task(
     timeout_ms: u32,
     per_host_interval: Duration,
     count: u32,
-    set: &mut JoinSet<(String, String, PingStatistics, Vec<(u16, Option<f64>, String)>)>,
+    pcap_writer: Option<Arc<pcap::PcapWriter>>,
+    stream_tx: Option<mpsc::UnboundedSender<String>>,
+    shutdown: Arc<utils::ShutdownController>,
+    ipc_state: Option<Arc<ipc::IpcState>>,
+    set: &mut JoinSet<(String, String, PingStatistics, Vec<(u16, Option<f64>, String, Option<u32>)>)>,
 ) {
     let name = job.name.clone();
     let ip = job.ip;
@@ -165,26 +355,208 @@ async fn spawn_host_task(
     let source_addr = args.source_address;
     let iface = args.interface.clone();
     let resolve_addrs = args.resolve_addresses;
-    let print_replies = !(args.summary_only || args.quiet || args.json_output || args.csv_output);
-    let print_headers = !(args.summary_only || args.json_output || args.csv_output);
-    let print_summaries = !(args.json_output || args.csv_output);
+    let print_replies = !(args.summary_only || args.quiet || args.json_output || args.csv_output || args.json_stream);
+    let print_headers = !(args.summary_only || args.json_output || args.csv_output || args.json_stream);
+    let print_summaries = !(args.json_output || args.csv_output || args.json_stream);
     let include_replies = args.include_replies;
+    let tcp_port = args.tcp_port;
+    let tos = args.tos.unwrap_or(0) as u8;
+    let embed_timestamp = args.timestamp_payload;
+    let fill_byte = args.payload_pattern.as_ref().and_then(|p| p.bytes().next()).unwrap_or(icmp::packet::PAYLOAD_FILL_BYTE);
+    let traceroute = args.traceroute;
+    let max_hops = args.max_hops;
+    let probes_per_hop = args.probes_per_hop.max(1);
+    let mtu_discover = args.mtu_discover;
+    let ipv4_options = icmp::socket::Ipv4OptionRequest {
+        record_route_hops: args.record_route,
+        timestamp_hops: args.timestamp,
+        timestamp_flags: 0,
+        loose_source_route: args.loose_source_route.as_ref().map(|hosts| parse_ipv4_host_list(hosts)),
+        strict_source_route: args.strict_source_route.as_ref().map(|hosts| parse_ipv4_host_list(hosts)),
+    };
 
     set.spawn(async move {
         let mut stats = PingStatistics::new();
         let identifier = utils::generate_identifier();
         let mut sequence: u16 = 1;
-        let mut replies: Vec<(u16, Option<f64>, String)> = Vec::new();
+        let mut replies: Vec<(u16, Option<f64>, String, Option<u32>)> = Vec::new();
 
         // 打印头部
         if print_headers {
             println!("{}", stats.format_header(&name, &ip.to_string(), payload_size as u32));
         }
 
-        // 优先尝试 RAW socket
+        // Traceroute 模式：递增 TTL/Hop Limit，解析沿途路由器返回的 ICMP 超时/不可达消息
+        if traceroute {
+            if let Ok(sock) = IcmpSocket::new(is_ipv6) {
+                // 非特权 datagram 套接字收不到中间路由器的 ICMP 错误消息，
+                // 继续跑下去只会让每一跳都显示超时——提前给出明确提示并跳过，
+                // 而不是静默地把每一跳都打印成 '*'
+                if sock.mode() == icmp::socket::IcmpSocketMode::Datagram {
+                    utils::print_warning(&format!("{}: traceroute 需要原始套接字，当前为非特权 datagram 套接字，无法接收中间路由器的 ICMP 错误消息，已跳过", name));
+                } else {
+                    'hops: for ttl in 1..=max_hops {
+                        if shutdown.is_draining() { break 'hops; }
+                        let mut hop_source: Option<IpAddr> = None;
+                        let mut rtts: Vec<Option<f64>> = Vec::with_capacity(probes_per_hop as usize);
+                        let mut reached = false;
+
+                        for _ in 0..probes_per_hop {
+                            stats.record_sent();
+                            match sock.send_traceroute_probe(ip, identifier, sequence, payload_size, timeout_ms, ttl).await {
+                                Ok(hop) => {
+                                    stats.record_received(hop.time_ms);
+                                    hop_source = Some(hop.source);
+                                    reached = hop.reached;
+                                    rtts.push(Some(hop.time_ms));
+                                }
+                                Err(_) => {
+                                    stats.record_lost();
+                                    rtts.push(None);
+                                }
+                            }
+                            sequence = sequence.wrapping_add(1);
+                        }
+
+                        if print_replies {
+                            let rtt_display: Vec<String> = rtts.iter().map(|r| match r {
+                                Some(ms) => utils::format_time(*ms),
+                                None => "*".to_string(),
+                            }).collect();
+                            match hop_source {
+                                Some(src) => println!("{:>3}  {}  {}", ttl, src, rtt_display.join("  ")),
+                                None => println!("{:>3}  {}  {}", ttl, "*", rtt_display.join("  ")),
+                            }
+                        }
+
+                        if reached {
+                            break 'hops;
+                        }
+                    }
+                }
+            } else {
+                utils::print_warning("无法创建 RAW 套接字，traceroute 需要管理员/root 权限");
+            }
+
+            if let Some(tx) = &stream_tx { let _ = tx.send(stream_event_summary(&name, &ip.to_string(), &stats)); }
+            if let Some(state) = &ipc_state { state.record_stats(&name, &ip.to_string(), &stats); }
+            if print_summaries {
+                println!("{}", stats.format_summary(&name));
+            }
+            return (name, ip.to_string(), stats, replies);
+        }
+
+        // 路径 MTU 发现：设置 DF 位，二分查找能不分片送达的最大负载
+        if mtu_discover {
+            if let Ok(sock) = IcmpSocket::new(is_ipv6) {
+                // 同 traceroute：datagram 套接字收不到 Fragmentation
+                // Needed/Packet Too Big 错误，二分查找会失去"广播上限"快速
+                // 路径，退化为缓慢的全超时探测——提前给出明确提示并跳过
+                if sock.mode() == icmp::socket::IcmpSocketMode::Datagram {
+                    utils::print_warning(&format!("{}: MTU 探测需要原始套接字，当前为非特权 datagram 套接字，无法接收 Fragmentation Needed/Packet Too Big 错误消息，已跳过", name));
+                } else {
+                    let overhead: usize = if is_ipv6 { 48 } else { 28 };
+                    let mut low: usize = 0;
+                    let mut high: usize = payload_size;
+                    let mut discovered: Option<usize> = None;
+
+                    while low < high {
+                        if shutdown.is_draining() { break; }
+                        let mid = low + (high - low + 1) / 2;
+                        stats.record_sent();
+                        match sock.send_mtu_probe(ip, identifier, sequence, mid, timeout_ms).await {
+                            Ok(icmp::MtuProbeOutcome::Delivered) => {
+                                stats.record_received(0.0);
+                                discovered = Some(mid);
+                                low = mid;
+                            }
+                            Ok(icmp::MtuProbeOutcome::FragmentationNeeded { next_hop_mtu }) => {
+                                stats.record_lost();
+                                let advertised_ceiling = next_hop_mtu.map(|mtu| (mtu as usize).saturating_sub(overhead));
+                                high = advertised_ceiling.unwrap_or(mid.saturating_sub(1)).min(mid.saturating_sub(1));
+                            }
+                            Err(_) => {
+                                stats.record_lost();
+                                high = mid.saturating_sub(1);
+                            }
+                        }
+                        sequence = sequence.wrapping_add(1);
+                    }
+
+                    if let Some(mtu_payload) = discovered {
+                        let path_mtu = mtu_payload as u32 + overhead as u32;
+                        stats.path_mtu = Some(path_mtu);
+                        if print_replies {
+                            println!("发现路径 MTU: {} 字节（负载 {} 字节 + 首部 {} 字节）", path_mtu, mtu_payload, overhead);
+                        }
+                    } else if print_replies {
+                        println!("无法确定路径 MTU，连最小探测负载都无法不分片送达。");
+                    }
+                }
+            } else {
+                utils::print_warning("无法创建 RAW 套接字，MTU 探测需要管理员/root 权限");
+            }
+
+            if let Some(tx) = &stream_tx { let _ = tx.send(stream_event_summary(&name, &ip.to_string(), &stats)); }
+            if let Some(state) = &ipc_state { state.record_stats(&name, &ip.to_string(), &stats); }
+            if print_summaries {
+                println!("{}", stats.format_summary(&name));
+            }
+            return (name, ip.to_string(), stats, replies);
+        }
+
+        // TCP SYN ping 模式：用 TCP 握手代替 ICMP 判断可达性
+        if let Some(port) = tcp_port {
+            for i in 0..count {
+                if shutdown.is_draining() { break; }
+                stats.record_sent();
+                match tcpping::tcp_ping(ip, port, timeout_ms).await {
+                    Ok(response) if response.port_closed => {
+                        stats.record_received(response.time_ms);
+                        if include_replies { replies.push((sequence, Some(response.time_ms), "port_closed".to_string(), None)); }
+                        if let Some(tx) = &stream_tx { let _ = tx.send(stream_event_reply(&name, &ip.to_string(), sequence, Some(response.time_ms), "port_closed", None)); }
+                        if print_replies { println!("来自 {}:{} 的回复: 端口已关闭（主机存活）时间={}", response.source, port, utils::format_time(response.time_ms)); }
+                    }
+                    Ok(response) => {
+                        stats.record_received(response.time_ms);
+                        if include_replies { replies.push((sequence, Some(response.time_ms), "ok".to_string(), None)); }
+                        if let Some(tx) = &stream_tx { let _ = tx.send(stream_event_reply(&name, &ip.to_string(), sequence, Some(response.time_ms), "ok", None)); }
+                        if print_replies { println!("来自 {}:{} 的回复: 时间={}", response.source, port, utils::format_time(response.time_ms)); }
+                    }
+                    Err(e) => {
+                        stats.record_lost();
+                        let out = if e.to_string().contains("timed out") { "timeout" } else { "error" };
+                        if include_replies { replies.push((sequence, None, out.to_string(), None)); }
+                        if let Some(tx) = &stream_tx { let _ = tx.send(stream_event_reply(&name, &ip.to_string(), sequence, None, out, None)); }
+                        if print_replies {
+                            if e.to_string().contains("timed out") { println!("请求超时。"); } else { eprintln!("错误: {}", e); }
+                        }
+                    }
+                }
+
+                sequence = sequence.wrapping_add(1);
+                if i < count - 1 || count == u32::MAX { sleep(per_host_interval).await; }
+            }
+
+            if let Some(tx) = &stream_tx { let _ = tx.send(stream_event_summary(&name, &ip.to_string(), &stats)); }
+            if let Some(state) = &ipc_state { state.record_stats(&name, &ip.to_string(), &stats); }
+            if print_summaries {
+                println!("{}", stats.format_summary(&name));
+            }
+            return (name, ip.to_string(), stats, replies);
+        }
+
+        // 优先尝试 RAW socket；若无权限，IcmpSocket::new 会在 Linux 上自动回退到
+        // 无需权限的 datagram ping socket
         #[allow(unused_mut)]
         let mut raw_socket = IcmpSocket::new(is_ipv6).ok();
 
+        if let Some(sock) = &raw_socket {
+            if sock.mode() == icmp::socket::IcmpSocketMode::Datagram && print_headers {
+                utils::print_warning("无 RAW 套接字权限，已回退到非特权的 ICMP datagram ping socket（部分功能如 IPv4 选项不可用）");
+            }
+        }
+
         // 绑定与 TTL（仅 RAW 可用）
         if let Some(sock) = &raw_socket {
             if let Some(sa) = source_addr {
@@ -200,19 +572,39 @@ async fn spawn_host_task(
 
         // WinAPI 回退（仅 IPv4）
         #[cfg(windows)]
-        let winapi_fallback = if raw_socket.is_none() && !is_ipv6 {
-            match icmp::winapi::WinApiIcmpSocket::new() { Ok(s) => Some(s), Err(e) => { utils::print_warning(&format!("WinAPI 回退创建失败: {}", e)); None } }
+        let winapi_fallback = if raw_socket.is_none() {
+            match icmp::winapi::WinApiIcmpSocket::new(is_ipv6) { Ok(s) => Some(s), Err(e) => { utils::print_warning(&format!("WinAPI 回退创建失败: {}", e)); None } }
         } else { None };
 
+        // 用于识别重复/乱序回复：记录最近见过的序号（有界窗口，见下方
+        // SEEN_SEQUENCE_WINDOW）与目前见过的最大序号
+        let mut seen_sequences: HashSet<u16> = HashSet::new();
+        let mut seen_sequence_order: VecDeque<u16> = VecDeque::new();
+        let mut highest_seen_sequence: Option<u16> = None;
+        // 用于检测路径是否存在负载均衡：记录首个回复的 TTL，一旦后续回复的
+        // TTL 与之不同就提示一次（不重复刷屏）
+        let mut first_seen_ttl: Option<u32> = None;
+        let mut warned_ttl_variance = false;
+
         for i in 0..count {
+            if shutdown.is_draining() { break; }
             stats.record_sent();
             let send_res = if let Some(sock) = &raw_socket {
-                sock.send_ping(ip, identifier, sequence, payload_size, timeout_ms).await
+                if !is_ipv6 && !ipv4_options.is_empty() {
+                    let src_v4 = match source_addr {
+                        Some(IpAddr::V4(addr)) => addr,
+                        _ => Ipv4Addr::UNSPECIFIED,
+                    };
+                    let ttl_u8 = ttl_opt.unwrap_or(128).min(255) as u8;
+                    sock.send_ping_with_ipv4_options(ip, src_v4, identifier, sequence, payload_size, timeout_ms, ttl_u8, tos, &ipv4_options, embed_timestamp, fill_byte, pcap_writer.as_deref()).await
+                } else {
+                    sock.send_ping(ip, identifier, sequence, payload_size, timeout_ms, embed_timestamp, fill_byte, pcap_writer.as_deref()).await
+                }
             } else {
                 #[cfg(windows)]
                 {
                     if let Some(ws) = &winapi_fallback {
-                        ws.send_ping(ip, identifier, sequence, payload_size, timeout_ms, ttl_opt).await
+                        ws.send_ping(ip, identifier, sequence, payload_size, timeout_ms, ttl_opt, source_addr).await
                     } else {
                         Err(anyhow::anyhow!("无法创建 ICMP 套接字且无可用回退"))
                     }
@@ -225,20 +617,74 @@ async fn spawn_host_task(
                 Ok(response) => {
                     // RAW 的时间在 send_ping 中已计算；WinAPI 我们也返回了 time_ms
                     stats.record_received(response.time_ms);
+
+                    // 回复实际携带的序号可能不是本次迭代期望的序号（重复/乱序）
+                    let is_dup = seen_sequences.contains(&response.sequence);
+                    // 普通整数比较在 --continuous 模式下序号从 65535 绕回 0 时会
+                    // 把每一个正常回复都误判为乱序；改用 TCP 序号比较的技巧——
+                    // 把差值当作有符号数解读，差值为负说明 response.sequence
+                    // 在模运算意义下落后于 highest_seen_sequence
+                    let is_reorder = !is_dup && highest_seen_sequence.map_or(false, |hs| {
+                        (response.sequence.wrapping_sub(hs) as i16) < 0
+                    });
+                    if is_dup {
+                        stats.record_duplicate();
+                    } else if is_reorder {
+                        stats.record_reorder();
+                        record_seen_sequence(&mut seen_sequences, &mut seen_sequence_order, response.sequence);
+                    } else {
+                        record_seen_sequence(&mut seen_sequences, &mut seen_sequence_order, response.sequence);
+                        highest_seen_sequence = Some(highest_seen_sequence.map_or(response.sequence, |hs| hs.max(response.sequence)));
+                    }
+                    if response.payload_corrupted {
+                        stats.record_corrupted();
+                    }
+
+                    let outcome = if is_dup { "dup" }
+                        else if is_reorder { "reorder" }
+                        else if response.payload_corrupted { "corrupted" }
+                        else { "ok" };
+                    // NAN 表示这次回复的真实 RTT 无法确定（迟到的旧序号回复，
+                    // 且未启用 --timestamp-payload），上报为缺失而不是一个编造的数字
+                    let reported_time_ms = if response.time_ms.is_nan() { None } else { Some(response.time_ms) };
                     if include_replies {
-                        replies.push((sequence, Some(response.time_ms), "ok".to_string()));
+                        replies.push((response.sequence, reported_time_ms, outcome.to_string(), Some(response.ttl)));
                     }
+                    if let Some(tx) = &stream_tx {
+                        let _ = tx.send(stream_event_reply(&name, &ip.to_string(), response.sequence, reported_time_ms, outcome, Some(response.ttl)));
+                    }
+
+                    // 同一目标的连续回复出现不同 TTL，通常意味着路径上存在
+                    // 负载均衡（不同回复走了跳数不同的路径）
+                    match first_seen_ttl {
+                        None => first_seen_ttl = Some(response.ttl),
+                        Some(first) if first != response.ttl && !warned_ttl_variance => {
+                            warned_ttl_variance = true;
+                            if print_replies {
+                                utils::print_warning(&format!("回复的 TTL 发生变化（{} -> {}），路径可能存在负载均衡", first, response.ttl));
+                            }
+                        }
+                        _ => {}
+                    }
+
                     if print_replies {
                         let resolved_name = if resolve_addrs { dns::reverse_lookup(response.source).await } else { None };
                         println!("{}", stats.format_response(&response, &name, resolved_name.as_deref()));
+                        if is_dup {
+                            println!("DUP! (序号 {} 重复)", response.sequence);
+                        } else if is_reorder {
+                            println!("REORDER! (序号 {} 乱序到达)", response.sequence);
+                        }
+                        if response.payload_corrupted {
+                            utils::print_warning("回复负载校验失败，数据可能在传输途中被篡改");
+                        }
                     }
                 }
                 Err(e) => {
                     stats.record_lost();
-                    if include_replies {
-                        let out = if e.to_string().contains("timed out") { "timeout" } else { "error" };
-                        replies.push((sequence, None, out.to_string()));
-                    }
+                    let out = if e.to_string().contains("timed out") { "timeout" } else { "error" };
+                    if include_replies { replies.push((sequence, None, out.to_string(), None)); }
+                    if let Some(tx) = &stream_tx { let _ = tx.send(stream_event_reply(&name, &ip.to_string(), sequence, None, out, None)); }
                     if print_replies {
                         if e.to_string().contains("timed out") { println!("请求超时。"); } else { eprintln!("错误: {}", e); }
                     }
@@ -251,6 +697,8 @@ async fn spawn_host_task(
         }
 
         // 每主机总结
+        if let Some(tx) = &stream_tx { let _ = tx.send(stream_event_summary(&name, &ip.to_string(), &stats)); }
+        if let Some(state) = &ipc_state { state.record_stats(&name, &ip.to_string(), &stats); }
         if print_summaries {
             println!("{}", stats.format_summary(&name));
         }
@@ -258,6 +706,48 @@ async fn spawn_host_task(
     });
 }
 
+/// Record a newly-seen sequence number in the bounded dup-detection window,
+/// evicting the oldest entry once `SEEN_SEQUENCE_WINDOW` is exceeded so the
+/// set can't grow for the lifetime of a long `--continuous` run.
+fn record_seen_sequence(seen: &mut HashSet<u16>, order: &mut VecDeque<u16>, sequence: u16) {
+    if seen.insert(sequence) {
+        order.push_back(sequence);
+        if order.len() > SEEN_SEQUENCE_WINDOW {
+            if let Some(oldest) = order.pop_front() {
+                seen.remove(&oldest);
+            }
+        }
+    }
+}
+
+fn now_epoch_ms() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0)
+}
+
+/// Build one compact NDJSON reply/timeout event for `--json-stream`.
+fn stream_event_reply(host: &str, ip: &str, seq: u16, time_ms: Option<f64>, outcome: &str, ttl: Option<u32>) -> String {
+    let ttl_json = ttl.map(|t| t.to_string()).unwrap_or_else(|| "null".to_string());
+    let time_json = time_ms.map(|v| format!("{:.3}", v)).unwrap_or_else(|| "null".to_string());
+    format!(
+        "{{\"ts\":{},\"host\":\"{}\",\"ip\":\"{}\",\"seq\":{},\"time_ms\":{},\"ttl\":{},\"outcome\":\"{}\"}}",
+        now_epoch_ms(), json_escape(host), json_escape(ip), seq, time_json, ttl_json, json_escape(outcome)
+    )
+}
+
+/// Build one compact NDJSON per-host summary event for `--json-stream`, reusing the
+/// same stats field set as the host entries in `build_json` so consumers see a stable schema.
+fn stream_event_summary(host: &str, ip: &str, s: &PingStatistics) -> String {
+    let path_mtu_json = s.path_mtu.map(|m| m.to_string()).unwrap_or_else(|| "null".to_string());
+    format!(
+        "{{\"type\":\"summary\",\"ts\":{},\"host\":\"{}\",\"ip\":\"{}\",\"sent\":{},\"received\":{},\"lost\":{},\"loss_pct\":{:.2},\"min\":{:.3},\"avg\":{:.3},\"max\":{:.3},\"p50\":{:.3},\"p90\":{:.3},\"p99\":{:.3},\"jitter\":{:.3},\"stddev\":{:.3},\"path_mtu\":{},\"duplicates\":{},\"reorders\":{},\"corrupted\":{}}}",
+        now_epoch_ms(), json_escape(host), json_escape(ip),
+        s.packets_sent, s.packets_received, s.packets_lost, s.loss_percentage(),
+        if s.min_time.is_finite() { s.min_time } else { 0.0 }, s.average_time(), s.max_time,
+        s.p50(), s.p90(), s.p99(), s.jitter(), s.std_deviation(),
+        path_mtu_json, s.duplicates, s.reorders, s.corrupted
+    )
+}
+
 fn json_escape(s: &str) -> String {
     let mut out = String::new();
     for c in s.chars() {
@@ -273,7 +763,7 @@ fn json_escape(s: &str) -> String {
     out
 }
 
-fn build_json(results: &Vec<(String, String, PingStatistics, Vec<(u16, Option<f64>, String)>)>, total: &PingStatistics, include_replies: bool, pretty: bool) -> String {
+fn build_json(results: &Vec<(String, String, PingStatistics, Vec<(u16, Option<f64>, String, Option<u32>)>)>, total: &PingStatistics, include_replies: bool, pretty: bool) -> String {
     let mut out = String::new();
     out.push_str("{\n  \"schema\":\"ruping-stats\",\n  \"version\":1,\n  \"hosts\":[\n");
     for (idx, (name, ip, s, reps)) in results.iter().enumerate() {
@@ -294,17 +784,23 @@ fn build_json(results: &Vec<(String, String, PingStatistics, Vec<(u16, Option<f6
         out.push_str(&format!("\"p90\":{:.3},", s.p90()));
         out.push_str(&format!("\"p99\":{:.3},", s.p99()));
         out.push_str(&format!("\"jitter\":{:.3},", s.jitter()));
-        out.push_str(&format!("\"stddev\":{:.3}", s.std_deviation()));
+        out.push_str(&format!("\"stddev\":{:.3},", s.std_deviation()));
+        match s.path_mtu {
+            Some(mtu) => out.push_str(&format!("\"path_mtu\":{},", mtu)),
+            None => out.push_str("\"path_mtu\":null,"),
+        }
+        out.push_str(&format!("\"duplicates\":{},\"reorders\":{},\"corrupted\":{}", s.duplicates, s.reorders, s.corrupted));
         if include_replies {
             out.push_str(",\"replies\":[");
-            for (i, (seq, rtt, outcome)) in reps.iter().enumerate() {
+            for (i, (seq, rtt, outcome, ttl)) in reps.iter().enumerate() {
                 if i > 0 { out.push(','); }
+                let ttl_json = ttl.map(|t| t.to_string()).unwrap_or_else(|| "null".to_string());
                 match rtt {
                     Some(v) => {
-                        out.push_str(&format!("{{\"seq\":{},\"time_ms\":{:.3},\"outcome\":\"{}\"}}", seq, v, json_escape(outcome)));
+                        out.push_str(&format!("{{\"seq\":{},\"time_ms\":{:.3},\"outcome\":\"{}\",\"ttl\":{}}}", seq, v, json_escape(outcome), ttl_json));
                     }
                     None => {
-                        out.push_str(&format!("{{\"seq\":{},\"time_ms\":null,\"outcome\":\"{}\"}}", seq, json_escape(outcome)));
+                        out.push_str(&format!("{{\"seq\":{},\"time_ms\":null,\"outcome\":\"{}\",\"ttl\":{}}}", seq, json_escape(outcome), ttl_json));
                     }
                 }
             }
@@ -324,7 +820,12 @@ fn build_json(results: &Vec<(String, String, PingStatistics, Vec<(u16, Option<f6
     out.push_str(&format!("\"p90\":{:.3},", total.p90()));
     out.push_str(&format!("\"p99\":{:.3},", total.p99()));
     out.push_str(&format!("\"jitter\":{:.3},", total.jitter()));
-    out.push_str(&format!("\"stddev\":{:.3}", total.std_deviation()));
+    out.push_str(&format!("\"stddev\":{:.3},", total.std_deviation()));
+    match total.path_mtu {
+        Some(mtu) => out.push_str(&format!("\"path_mtu\":{},", mtu)),
+        None => out.push_str("\"path_mtu\":null,"),
+    }
+    out.push_str(&format!("\"duplicates\":{},\"reorders\":{},\"corrupted\":{}", total.duplicates, total.reorders, total.corrupted));
     out.push_str("}\n}\n");
     if !pretty {
         // 紧凑化：移除换行与多余空格
@@ -336,34 +837,39 @@ fn build_json(results: &Vec<(String, String, PingStatistics, Vec<(u16, Option<f6
     }
 }
 
-fn build_csv(results: &Vec<(String, String, PingStatistics, Vec<(u16, Option<f64>, String)>)>, total: &PingStatistics, include_replies: bool, no_headers: bool) -> String {
+fn build_csv(results: &Vec<(String, String, PingStatistics, Vec<(u16, Option<f64>, String, Option<u32>)>)>, total: &PingStatistics, include_replies: bool, no_headers: bool) -> String {
     let mut out = String::new();
     if !no_headers {
-        out.push_str("scope,name,ip,sent,received,lost,loss_pct,min,avg,max,p50,p90,p99,jitter,stddev\n");
-        if include_replies { out.push_str("scope,name,ip,seq,time_ms,outcome\n"); }
+        out.push_str("scope,name,ip,sent,received,lost,loss_pct,min,avg,max,p50,p90,p99,jitter,stddev,path_mtu,duplicates,reorders,corrupted\n");
+        if include_replies { out.push_str("scope,name,ip,seq,time_ms,outcome,ttl\n"); }
     }
     for (name, ip, s, reps) in results {
         out.push_str(&format!(
-            "host,{},{},{},{},{},{:.2},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}\n",
+            "host,{},{},{},{},{},{:.2},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{},{},{},{}\n",
             name, ip,
             s.packets_sent, s.packets_received, s.packets_lost, s.loss_percentage(),
             if s.min_time.is_finite() { s.min_time } else { 0.0 }, s.average_time(), s.max_time,
-            s.p50(), s.p90(), s.p99(), s.jitter(), s.std_deviation()
+            s.p50(), s.p90(), s.p99(), s.jitter(), s.std_deviation(),
+            s.path_mtu.map(|m| m.to_string()).unwrap_or_default(),
+            s.duplicates, s.reorders, s.corrupted
         ));
         if include_replies {
-            for (seq, rtt, outcome) in reps {
+            for (seq, rtt, outcome, ttl) in reps {
+                let ttl_display = ttl.map(|t| t.to_string()).unwrap_or_default();
                 match rtt {
-                    Some(v) => out.push_str(&format!("reply,{},{},{},{:.3},{}\n", name, ip, seq, v, outcome)),
-                    None => out.push_str(&format!("reply,{},{},{},,{}\n", name, ip, seq, outcome)),
+                    Some(v) => out.push_str(&format!("reply,{},{},{},{:.3},{},{}\n", name, ip, seq, v, outcome, ttl_display)),
+                    None => out.push_str(&format!("reply,{},{},{},,{},{}\n", name, ip, seq, outcome, ttl_display)),
                 }
             }
         }
     }
     out.push_str(&format!(
-        "overall,,,{},{},{},{:.2},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}\n",
+        "overall,,,{},{},{},{:.2},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{},{},{},{}\n",
         total.packets_sent, total.packets_received, total.packets_lost, total.loss_percentage(),
         if total.min_time.is_finite() { total.min_time } else { 0.0 }, total.average_time(), total.max_time,
-        total.p50(), total.p90(), total.p99(), total.jitter(), total.std_deviation()
+        total.p50(), total.p90(), total.p99(), total.jitter(), total.std_deviation(),
+        total.path_mtu.map(|m| m.to_string()).unwrap_or_default(),
+        total.duplicates, total.reorders, total.corrupted
     ));
     out
 }
@@ -380,7 +886,22 @@ fn read_targets_from_file(path: &str) -> Vec<String> {
     }
 }
 
-fn expand_cidr_ipv4(cidr: &str) -> Vec<String> {
+fn parse_ipv4_host_list(hosts: &[String]) -> Vec<Ipv4Addr> {
+    hosts.iter().filter_map(|h| match h.parse::<Ipv4Addr>() {
+        Ok(addr) => Some(addr),
+        Err(_) => { utils::print_warning(&format!("源路由主机 '{}' 不是合法的 IPv4 地址，已忽略", h)); None }
+    }).collect()
+}
+
+fn expand_cidr(cidr: &str, max_hosts: u64) -> Vec<String> {
+    if cidr.contains(':') {
+        expand_cidr_ipv6(cidr, max_hosts)
+    } else {
+        expand_cidr_ipv4(cidr, max_hosts)
+    }
+}
+
+fn expand_cidr_ipv4(cidr: &str, max_hosts: u64) -> Vec<String> {
     let mut out = Vec::new();
     let parts: Vec<&str> = cidr.split('/').collect();
     if parts.len() != 2 { return out; }
@@ -389,7 +910,64 @@ fn expand_cidr_ipv4(cidr: &str) -> Vec<String> {
     if prefix > 32 { return out; }
     let base_u32 = u32::from(base) & (!0u32 << (32 - prefix));
     let host_count = if prefix == 32 { 1 } else { 1u64 << (32 - prefix) };
+    if host_count > max_hosts {
+        eprintln!("CIDR '{}' 展开后的主机数 ({}) 超过上限 ({})，已拒绝展开。请使用更长的前缀或调整 --max-hosts。", cidr, host_count, max_hosts);
+        return out;
+    }
     // 简单全量展开（包含网络和广播地址）
     for i in 0..host_count { out.push(Ipv4Addr::from(base_u32.wrapping_add(i as u32)).to_string()); }
     out
 }
+
+fn expand_cidr_ipv6(cidr: &str, max_hosts: u64) -> Vec<String> {
+    use std::net::Ipv6Addr;
+    let mut out = Vec::new();
+    let parts: Vec<&str> = cidr.split('/').collect();
+    if parts.len() != 2 { return out; }
+    let base: Ipv6Addr = match parts[0].parse() { Ok(ip) => ip, Err(_) => return out };
+    let prefix: u32 = match parts[1].parse() { Ok(p) => p, Err(_) => return out };
+    if prefix > 128 { return out; }
+    let base_u128 = u128::from_be_bytes(base.octets()) & (!0u128 << (128 - prefix));
+    let host_count: u128 = if prefix == 128 { 1 } else { 1u128 << (128 - prefix) };
+    if host_count > max_hosts as u128 {
+        eprintln!("CIDR '{}' 展开后的主机数 ({}) 超过上限 ({})，已拒绝展开。请使用更长的前缀或调整 --max-hosts。", cidr, host_count, max_hosts);
+        return out;
+    }
+    for i in 0..host_count {
+        out.push(Ipv6Addr::from(base_u128.wrapping_add(i)).to_string());
+    }
+    out
+}
+
+fn expand_range(range: &str, max_hosts: u64) -> Vec<String> {
+    let mut out = Vec::new();
+    let parts: Vec<&str> = range.splitn(2, '-').collect();
+    if parts.len() != 2 { eprintln!("无法解析地址范围 '{}'，期望格式为 START-END。", range); return out; }
+
+    match (parts[0].trim().parse::<IpAddr>(), parts[1].trim().parse::<IpAddr>()) {
+        (Ok(IpAddr::V4(start)), Ok(IpAddr::V4(end))) => {
+            let (s, e) = (u32::from(start), u32::from(end));
+            if e < s { eprintln!("地址范围 '{}' 的结束地址小于起始地址。", range); return out; }
+            let host_count = (e - s) as u64 + 1;
+            if host_count > max_hosts {
+                eprintln!("地址范围 '{}' 展开后的主机数 ({}) 超过上限 ({})，已拒绝展开。", range, host_count, max_hosts);
+                return out;
+            }
+            for v in s..=e { out.push(Ipv4Addr::from(v).to_string()); }
+        }
+        (Ok(IpAddr::V6(start)), Ok(IpAddr::V6(end))) => {
+            use std::net::Ipv6Addr;
+            let (s, e) = (u128::from_be_bytes(start.octets()), u128::from_be_bytes(end.octets()));
+            if e < s { eprintln!("地址范围 '{}' 的结束地址小于起始地址。", range); return out; }
+            let host_count = e - s + 1;
+            if host_count > max_hosts as u128 {
+                eprintln!("地址范围 '{}' 展开后的主机数 ({}) 超过上限 ({})，已拒绝展开。", range, host_count, max_hosts);
+                return out;
+            }
+            let mut v = s;
+            while v <= e { out.push(Ipv6Addr::from(v).to_string()); if v == e { break; } v += 1; }
+        }
+        _ => { eprintln!("地址范围 '{}' 的起止地址版本不一致或无法解析。", range); }
+    }
+    out
+}
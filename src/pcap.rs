@@ -0,0 +1,58 @@
+// Minimal libpcap capture-file writer for `--pcap PATH`, so a run can be
+// opened directly in Wireshark/tshark for offline analysis. Every record is
+// declared as a bare IP datagram (LINKTYPE_RAW); callers in `icmp::socket`
+// are responsible for handing us bytes that actually start with a real IP
+// header, synthesizing one first on paths (an ordinary send, or a raw
+// ICMPv6 receive) where the OS never includes it.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+const PCAP_VERSION_MAJOR: u16 = 2;
+const PCAP_VERSION_MINOR: u16 = 4;
+const SNAPLEN: u32 = 65535;
+const LINKTYPE_RAW: u32 = 101;
+
+/// A libpcap writer shared across concurrent host tasks; each capture is
+/// serialized behind a mutex since packet order only matters per-host and
+/// Wireshark sorts by timestamp on load anyway.
+pub struct PcapWriter {
+    file: Mutex<File>,
+}
+
+impl PcapWriter {
+    pub fn create(path: &str) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&PCAP_MAGIC.to_le_bytes());
+        header.extend_from_slice(&PCAP_VERSION_MAJOR.to_le_bytes());
+        header.extend_from_slice(&PCAP_VERSION_MINOR.to_le_bytes());
+        header.extend_from_slice(&0i32.to_le_bytes()); // thiszone
+        header.extend_from_slice(&0u32.to_le_bytes()); // sigfigs
+        header.extend_from_slice(&SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        file.write_all(&header)?;
+
+        Ok(Self { file: Mutex::new(file) })
+    }
+
+    /// Append one captured packet (sent probe or received reply) as a raw IP datagram.
+    pub fn write_packet(&self, data: &[u8]) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut record = Vec::with_capacity(16 + data.len());
+        record.extend_from_slice(&(now.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&now.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes()); // incl_len
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes()); // orig_len
+        record.extend_from_slice(data);
+
+        match self.file.lock() {
+            Ok(mut file) => { if let Err(e) = file.write_all(&record) { crate::utils::print_warning(&format!("写入 pcap 文件失败: {}", e)); } }
+            Err(_) => {}
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::time::Instant;
+use crate::utils::{format_duration, Precision};
 
 #[derive(Debug, Clone)]
 pub struct PingStatistics {
@@ -14,6 +15,17 @@ pub struct PingStatistics {
     last_time_ms: Option<f64>,
     jitter_sum: f64,
     jitter_count: u32,
+    /// Path MTU discovered via `--mtu-discover`, if that mode was used.
+    pub path_mtu: Option<u32>,
+    /// Replies whose sequence number had already been seen (the same echo
+    /// answered more than once, typically from a multipath duplicate).
+    pub duplicates: u32,
+    /// Replies that arrived with a sequence number lower than the highest
+    /// one already seen for this host.
+    pub reorders: u32,
+    /// Replies whose payload failed `IcmpPacket::verify_payload_pattern`,
+    /// meaning something on the path rewrote it in transit.
+    pub corrupted: u32,
 }
 
 impl PingStatistics {
@@ -30,8 +42,24 @@ impl PingStatistics {
             last_time_ms: None,
             jitter_sum: 0.0,
             jitter_count: 0,
+            path_mtu: None,
+            duplicates: 0,
+            reorders: 0,
+            corrupted: 0,
         }
     }
+
+    pub fn record_duplicate(&mut self) {
+        self.duplicates += 1;
+    }
+
+    pub fn record_reorder(&mut self) {
+        self.reorders += 1;
+    }
+
+    pub fn record_corrupted(&mut self) {
+        self.corrupted += 1;
+    }
     
     pub fn record_sent(&mut self) {
         self.packets_sent += 1;
@@ -39,6 +67,13 @@ impl PingStatistics {
     
     pub fn record_received(&mut self, time_ms: f64) {
         self.packets_received += 1;
+        // NAN marks a reply whose real RTT couldn't be determined (a stray
+        // match to an earlier probe with no embedded timestamp to fall back
+        // on, see `IcmpSocket::send_ping`) — it still counts as received,
+        // but must not pollute min/max/avg/jitter with a fabricated number.
+        if time_ms.is_nan() {
+            return;
+        }
         self.total_time += time_ms;
 
         if time_ms < self.min_time {
@@ -120,6 +155,13 @@ impl PingStatistics {
         // 合并抖动（按样本-1 计数加权）
         self.jitter_sum += other.jitter_sum;
         self.jitter_count += other.jitter_count;
+        // 多个主机都做了 MTU 发现时，取路径上的瓶颈（最小值）
+        if let Some(mtu) = other.path_mtu {
+            self.path_mtu = Some(self.path_mtu.map_or(mtu, |cur| cur.min(mtu)));
+        }
+        self.duplicates += other.duplicates;
+        self.reorders += other.reorders;
+        self.corrupted += other.corrupted;
     }
     
     pub fn format_summary(&self, target: &str) -> String {
@@ -137,32 +179,47 @@ impl PingStatistics {
         if self.packets_received > 0 {
             let min_time = if self.min_time == f64::INFINITY { 0.0 } else { self.min_time };
             summary.push_str(&format!(
-                "往返行程的估计时间(以毫秒为单位):\n    最短 = {:.0}ms，最长 = {:.0}ms，平均 = {:.0}ms\n",
-                min_time,
-                self.max_time,
-                self.average_time()
+                "往返行程的估计时间:\n    最短 = {}，最长 = {}，平均 = {}\n",
+                format_duration(min_time, Precision::WholeMillis),
+                format_duration(self.max_time, Precision::WholeMillis),
+                format_duration(self.average_time(), Precision::WholeMillis)
             ));
 
             // 高级统计
             if self.samples.len() >= 1 {
                 summary.push_str(&format!(
-                    "    P50 = {:.0}ms，P90 = {:.0}ms，P99 = {:.0}ms",
-                    self.p50(), self.p90(), self.p99()
+                    "    P50 = {}，P90 = {}，P99 = {}",
+                    format_duration(self.p50(), Precision::WholeMillis),
+                    format_duration(self.p90(), Precision::WholeMillis),
+                    format_duration(self.p99(), Precision::WholeMillis)
                 ));
                 if self.samples.len() >= 2 {
                     summary.push_str(&format!(
-                        "，Jitter = {:.1}ms，StdDev = {:.1}ms\n",
-                        self.jitter(), self.std_deviation()
+                        "，Jitter = {}，StdDev = {}\n",
+                        format_duration(self.jitter(), Precision::WholeMillis),
+                        format_duration(self.std_deviation(), Precision::WholeMillis)
                     ));
                 } else {
                     summary.push('\n');
                 }
             }
         }
-        
+
+        if let Some(mtu) = self.path_mtu {
+            summary.push_str(&format!("路径 MTU: {} 字节\n", mtu));
+        }
+
+        if self.duplicates > 0 || self.reorders > 0 {
+            summary.push_str(&format!("    重复回复 = {}，乱序回复 = {}\n", self.duplicates, self.reorders));
+        }
+
+        if self.corrupted > 0 {
+            summary.push_str(&format!("    校验失败(可能被篡改)的回复 = {}\n", self.corrupted));
+        }
+
         summary
     }
-    
+
     pub fn format_response(&self, response: &crate::icmp::IcmpResponse, _target: &str, resolved_name: Option<&str>) -> String {
         let source_display = if let Some(name) = resolved_name {
             format!("{} [{}]", name, response.source)
@@ -170,10 +227,10 @@ impl PingStatistics {
             response.source.to_string()
         };
         
-        let time_display = if response.time_ms < 1.0 {
-            "<1ms".to_string()
+        let time_display = if response.time_ms.is_nan() {
+            "未知".to_string()
         } else {
-            format!("{:.0}ms", response.time_ms)
+            format_duration(response.time_ms, Precision::Auto)
         };
         
         format!(
@@ -237,6 +294,10 @@ mod tests {
             time_ms: 15.7,
             ttl: 64,
             sequence: 1,
+            recorded_route: Vec::new(),
+            timestamps: Vec::new(),
+            payload_corrupted: false,
+            embedded_rtt_ms: None,
         };
         
         let formatted = stats.format_response(&response, "8.8.8.8", None);
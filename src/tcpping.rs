@@ -0,0 +1,45 @@
+// TCP "SYN ping" connectivity mode, for hosts whose firewalls silently drop
+// ICMP echo. Reachability is inferred from the TCP handshake instead: a
+// completed `connect()` means the port (and therefore the host) is up, and
+// an RST (`ConnectionRefused`) means the host is up but the port is closed.
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+#[derive(Debug, Clone)]
+pub struct TcpPingResponse {
+    pub source: IpAddr,
+    pub time_ms: f64,
+    /// `true` when the remote host answered with RST (port closed but reachable).
+    pub port_closed: bool,
+}
+
+/// Attempt a single TCP handshake against `target:port`, measuring RTT from
+/// the start of the non-blocking `connect()` to success or RST.
+pub async fn tcp_ping(target: IpAddr, port: u16, timeout_ms: u32) -> anyhow::Result<TcpPingResponse> {
+    let addr = SocketAddr::new(target, port);
+    let start = Instant::now();
+
+    match timeout(Duration::from_millis(timeout_ms as u64), TcpStream::connect(addr)).await {
+        Ok(Ok(_stream)) => Ok(TcpPingResponse {
+            source: target,
+            time_ms: start.elapsed().as_secs_f64() * 1000.0,
+            port_closed: false,
+        }),
+        Ok(Err(e)) => {
+            if e.kind() == std::io::ErrorKind::ConnectionRefused {
+                // RST: host is up, nothing is listening on this port.
+                Ok(TcpPingResponse {
+                    source: target,
+                    time_ms: start.elapsed().as_secs_f64() * 1000.0,
+                    port_closed: true,
+                })
+            } else {
+                Err(anyhow::anyhow!("TCP 连接失败: {}", e))
+            }
+        }
+        Err(_) => Err(anyhow::anyhow!("Request timed out")),
+    }
+}
@@ -1,4 +1,7 @@
 use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
 
 /// Generate a random identifier for ICMP packets
 pub fn generate_identifier() -> u16 {
@@ -6,45 +9,53 @@ pub fn generate_identifier() -> u16 {
     rand::thread_rng().gen_range(1..=65535)
 }
 
-/// Check if the current process has administrator privileges on Windows
+/// Check if *some* usable ICMP path is available without elevated privileges
+/// failing outright — `IcmpSocket::new` already tries a raw socket and, on
+/// Linux, the unprivileged datagram ping-socket fallback.
 pub fn check_admin_privileges() -> bool {
-    // On Windows, we can try to create a raw socket to check privileges
     crate::icmp::socket::check_raw_socket_privileges()
 }
 
-/// Detailed privilege check with error reporting
+/// Detailed privilege check with error reporting. Tries every available
+/// unprivileged path before giving up: a raw socket, then (on Linux) the
+/// datagram ping-socket fallback already built into `IcmpSocket::new`, then
+/// (on Windows) the `IcmpSendEcho`/`Icmp6SendEcho2` WinAPI fallback. Only
+/// when every one of those fails for both address families do we report the
+/// full troubleshooting wall of text.
 pub fn check_privileges_detailed() -> anyhow::Result<()> {
-    // First check if we can create a raw socket
-    match crate::icmp::socket::IcmpSocket::new(false) {
-        Ok(_) => {
-            //println!("✅ IPv4 Raw Socket权限检查通过");
-            return Ok(());
-        }
-        Err(e) => {
-            eprintln!("❌ IPv4 Raw Socket Check Failed: {}", e);
-        }
-    }
+    // IcmpSocket::new already falls back from raw to the unprivileged Linux
+    // datagram ping socket, so a single Ok here covers both paths.
+    let v4_err = match crate::icmp::socket::IcmpSocket::new(false) {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+    let v6_err = match crate::icmp::socket::IcmpSocket::new(true) {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+    let last_error = format!("IPv4: {}; IPv6: {}", v4_err, v6_err);
 
-    // Try IPv6
-    match crate::icmp::socket::IcmpSocket::new(true) {
-        Ok(_) => {
-            //println!("✅ IPv6 Raw Socket权限检查通过");
+    #[cfg(windows)]
+    {
+        // WinAPI ICMP.dll sends echoes from a non-admin process.
+        if crate::icmp::winapi::WinApiIcmpSocket::new(false).is_ok() || crate::icmp::winapi::WinApiIcmpSocket::new(true).is_ok() {
             return Ok(());
         }
-        Err(e) => {
-            eprintln!("❌ IPv6 Raw Socket Check Failed: {}", e);
-        }
     }
 
-    // If both fail, provide detailed error information
+    eprintln!("❌ ICMP Socket Check Failed: {}", last_error);
+
+    // If every unprivileged path fails too, provide detailed error information
     Err(anyhow::anyhow!(
-        "无法创建Raw Socket。可能的原因:\n\
+        "无法创建任何可用的 ICMP 套接字（Raw、非特权 datagram ping socket{} 均不可用）。可能的原因:\n\
         1. 需要管理员权限 - 请以管理员身份运行\n\
         2. Windows防火墙阻止 - 请检查防火墙设置\n\
         3. 杀毒软件阻止 - 请临时禁用杀毒软件\n\
         4. 组策略限制 - 请检查本地安全策略\n\
-        5. 网络驱动问题 - 请更新网络驱动程序\n\n\
-        请运行 diagnose_permissions.ps1 进行详细诊断"
+        5. 网络驱动问题 - 请更新网络驱动程序\n\
+        6. (Linux) net.ipv4.ping_group_range 未包含当前用户组 - 可尝试 sudo sysctl -w net.ipv4.ping_group_range=\"0 2147483647\"\n\n\
+        请运行 diagnose_permissions.ps1 进行详细诊断",
+        if cfg!(windows) { "、WinAPI ICMP.dll" } else { "" }
     ))
 }
 
@@ -99,25 +110,121 @@ pub fn validate_ping_params(
     Ok(())
 }
 
-/// Format time duration for display
-pub fn format_time(ms: f64) -> String {
+/// Unit granularity hint for `format_duration`. Summary lines and per-reply
+/// lines want different tradeoffs: a single reply benefits from
+/// sub-millisecond resolution on a fast LAN, while a statistics summary is
+/// usually clearer rounded to whole milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    /// Pick the best unit (µs/ms/s) for the magnitude.
+    Auto,
+    /// Always render as whole milliseconds, regardless of magnitude.
+    WholeMillis,
+}
+
+/// Format a duration given in milliseconds, adaptively picking µs/ms/s based
+/// on magnitude instead of collapsing everything under 1ms to `"<1ms"`. Below
+/// 1ms values render as whole microseconds (e.g. `842µs`); from 1ms up to
+/// 10ms two fractional digits are kept (e.g. `3.47ms`) so fast-LAN
+/// variation stays visible; at 10ms and above values
+/// round to whole milliseconds, and at 1 second and above they switch to
+/// seconds with two fractional digits (e.g. `1.20s`).
+pub fn format_duration(ms: f64, precision: Precision) -> String {
+    if precision == Precision::WholeMillis {
+        return format!("{:.0}ms", ms);
+    }
     if ms < 1.0 {
-        "<1ms".to_string()
-    } else {
+        format!("{:.0}µs", ms * 1000.0)
+    } else if ms < 10.0 {
+        format!("{:.2}ms", ms)
+    } else if ms < 1000.0 {
         format!("{:.0}ms", ms)
+    } else {
+        format!("{:.2}s", ms / 1000.0)
     }
 }
 
-/// Handle Ctrl+C signal for graceful shutdown
-pub fn setup_signal_handler() -> tokio::sync::oneshot::Receiver<()> {
-    let (tx, rx) = tokio::sync::oneshot::channel();
-    
-    tokio::spawn(async move {
-        tokio::signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
-        let _ = tx.send(());
-    });
-    
-    rx
+/// Backward-compatible alias for `format_duration(ms, Precision::Auto)`, kept
+/// so existing call sites compile unchanged.
+pub fn format_time(ms: f64) -> String {
+    format_duration(ms, Precision::Auto)
+}
+
+/// What `ShutdownController` tells a ping worker to do when Ctrl+C fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownSignal {
+    /// Stop sending new probes, let any in-flight reply finish, then print
+    /// the final summary and return — the first Ctrl+C.
+    Drain,
+    /// Stop immediately; a second Ctrl+C arrived before draining finished.
+    Abort,
+}
+
+/// Coordinates a graceful shutdown across every concurrent ping worker via a
+/// `tokio::sync::broadcast` channel, so any number of them can `subscribe()`
+/// and learn about it together — unlike the single-consumer oneshot this
+/// replaces. The first Ctrl+C (or a programmatic `trigger()`) broadcasts
+/// `Drain`; a second one broadcasts `Abort` and exits the process outright.
+pub struct ShutdownController {
+    tx: broadcast::Sender<ShutdownSignal>,
+    draining: AtomicBool,
+}
+
+impl ShutdownController {
+    /// Build the controller and start listening for Ctrl+C in the
+    /// background. If signal registration itself fails, this logs a warning
+    /// and degrades to trigger-only: callers embedding ruping as a library
+    /// can still call `trigger()` directly, they just won't get it for free
+    /// from Ctrl+C.
+    pub fn new() -> Arc<Self> {
+        let (tx, _rx) = broadcast::channel(16);
+        let controller = Arc::new(Self { tx, draining: AtomicBool::new(false) });
+
+        let listener = controller.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = tokio::signal::ctrl_c().await {
+                    print_warning(&format!("无法注册 Ctrl+C 信号处理器: {}，优雅关闭现在只能通过编程方式触发", e));
+                    return;
+                }
+                listener.trigger();
+            }
+        });
+
+        controller
+    }
+
+    /// A receiver for the `Drain`/`Abort` signals broadcast after this call;
+    /// it sees nothing sent before it subscribed (see `is_draining`).
+    pub fn subscribe(&self) -> broadcast::Receiver<ShutdownSignal> {
+        self.tx.subscribe()
+    }
+
+    /// Level-triggered check for "has a shutdown been requested at all".
+    /// Unlike polling a `broadcast::Receiver` (which only consumes a message
+    /// still queued for that specific receiver, and sees nothing if the
+    /// receiver was only subscribed *after* the broadcast went out), this
+    /// reads the same `AtomicBool` every caller shares, so it keeps
+    /// returning `true` after the first Ctrl+C no matter when the caller
+    /// started watching. Prefer this for anything that needs to notice an
+    /// already-in-progress drain, such as a scheduler deciding whether to
+    /// dispatch new work or a task spawned after the signal fired.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Request a shutdown. The first call broadcasts `Drain`; any call after
+    /// that — a second Ctrl+C arriving before workers finished draining —
+    /// broadcasts `Abort` and exits the process immediately.
+    pub fn trigger(&self) {
+        if self.draining.swap(true, Ordering::SeqCst) {
+            let _ = self.tx.send(ShutdownSignal::Abort);
+            eprintln!("再次收到中断信号，立即退出。");
+            process::exit(130);
+        } else {
+            let _ = self.tx.send(ShutdownSignal::Drain);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -153,9 +260,41 @@ mod tests {
     
     #[test]
     fn test_time_formatting() {
-        assert_eq!(format_time(0.5), "<1ms");
-        assert_eq!(format_time(1.0), "1ms");
+        assert_eq!(format_time(0.5), "500µs");
+        assert_eq!(format_time(1.0), "1.00ms");
         assert_eq!(format_time(15.7), "16ms");
         assert_eq!(format_time(100.0), "100ms");
     }
+
+    #[test]
+    fn test_format_duration_microseconds_below_one_millisecond() {
+        assert_eq!(format_duration(0.0, Precision::Auto), "0µs");
+        assert_eq!(format_duration(0.842, Precision::Auto), "842µs");
+        assert_eq!(format_duration(0.999, Precision::Auto), "999µs");
+    }
+
+    #[test]
+    fn test_format_duration_two_sig_figs_below_ten_milliseconds() {
+        assert_eq!(format_duration(1.0, Precision::Auto), "1.00ms");
+        assert_eq!(format_duration(3.47, Precision::Auto), "3.47ms");
+        assert_eq!(format_duration(9.994, Precision::Auto), "9.99ms");
+    }
+
+    #[test]
+    fn test_format_duration_whole_millis_at_and_above_ten_milliseconds() {
+        assert_eq!(format_duration(10.0, Precision::Auto), "10ms");
+        assert_eq!(format_duration(999.4, Precision::Auto), "999ms");
+    }
+
+    #[test]
+    fn test_format_duration_seconds_at_and_above_one_second() {
+        assert_eq!(format_duration(1000.0, Precision::Auto), "1.00s");
+        assert_eq!(format_duration(1204.0, Precision::Auto), "1.20s");
+    }
+
+    #[test]
+    fn test_format_duration_whole_millis_precision_ignores_unit_selection() {
+        assert_eq!(format_duration(0.5, Precision::WholeMillis), "0ms");
+        assert_eq!(format_duration(1500.0, Precision::WholeMillis), "1500ms");
+    }
 }